@@ -0,0 +1,143 @@
+use crate::card::Card;
+use crate::deck;
+use crate::input::{read_key, Add};
+use crate::logger;
+use crossterm::event::KeyCode;
+use log::info;
+use rand::Rng;
+use std::process::exit;
+use undo::Record;
+
+const WIDTH: usize = 13;
+
+pub fn start_game(mut game: u64) {
+    if game == 0 {
+        let mut rng = rand::thread_rng();
+        game = rng.gen_range(1, 1000000);
+    }
+    let mut piles = set_up(game);
+    let mut cursor = piles.len() - 1;
+    let mut record = Record::default();
+    match record.apply(Add(piles.clone())) {
+        Ok(_) => {}
+        Err(_) => exit(1),
+    }
+
+    print!("\x1B[2J\x1B[1;1H");
+    deck::print_row(&piles, WIDTH, cursor);
+    info!("New Game!{}", logger::row_to_log(&piles, WIDTH));
+
+    loop {
+        if game_over(&piles) {
+            println!("Game Over! Piles left: {}", piles.len());
+            info!(
+                "Game Over! Piles left: {}{}",
+                piles.len(),
+                logger::row_to_log(&piles, WIDTH)
+            );
+            return;
+        }
+        let key = read_key();
+        print!("\x1B[2J\x1B[1;1H");
+        match key {
+            KeyCode::Left => cursor = cursor.saturating_sub(1),
+            KeyCode::Right => cursor = (cursor + 1).min(piles.len() - 1),
+            KeyCode::Char('1') | KeyCode::Char('3') => {
+                let distance = if key == KeyCode::Char('1') { 1 } else { 3 };
+                if move_pile(&mut piles, cursor, distance) {
+                    match record.apply(Add(piles.clone())) {
+                        Ok(_) => {}
+                        Err(_) => exit(1),
+                    }
+                    info!(
+                        "From {} to {}{}",
+                        cursor + 1,
+                        cursor + 1 - distance,
+                        logger::row_to_log(&piles, WIDTH)
+                    );
+                    cursor -= distance;
+                } else {
+                    println!("No moves");
+                }
+            }
+            KeyCode::Char('u') | KeyCode::Char('U') => {
+                if record.current() > 1 {
+                    match record.undo() {
+                        Ok(_) => {}
+                        Err(_) => exit(1),
+                    }
+                    restore(&mut piles, &record);
+                    info!("Undo{}", logger::row_to_log(&piles, WIDTH));
+                }
+            }
+            KeyCode::Char('i') | KeyCode::Char('I') => {
+                if record.current() < record.len() {
+                    match record.redo() {
+                        Ok(_) => {}
+                        Err(_) => exit(1),
+                    }
+                    restore(&mut piles, &record);
+                    info!("Redo{}", logger::row_to_log(&piles, WIDTH));
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') => {
+                start_game(0);
+                return;
+            }
+            KeyCode::Esc => return,
+            _ => println!("Invalid command"),
+        }
+        cursor = cursor.min(piles.len() - 1);
+        deck::print_row(&piles, WIDTH, cursor);
+    }
+}
+
+fn set_up(game: u64) -> Vec<Vec<Card>> {
+    let mut initial_deck = deck::new_deck(1);
+    deck::shuffle_deck(&mut initial_deck, game);
+    initial_deck
+        .into_iter()
+        .map(|mut c| {
+            c.show();
+            vec![c]
+        })
+        .collect()
+}
+
+fn restore(piles: &mut Vec<Vec<Card>>, record: &Record<Vec<Vec<Vec<Card>>>>) {
+    match record.target().get(record.current() - 1) {
+        Some(r) => *piles = r.clone(),
+        None => exit(1),
+    }
+}
+
+/// Two piles match when their top cards share suit or rank.
+fn matches(piles: &[Vec<Card>], source: usize, target: usize) -> bool {
+    match (piles[source].last(), piles[target].last()) {
+        (Some(a), Some(b)) => {
+            a.get_card_suit() == b.get_card_suit() || a.get_card_value() == b.get_card_value()
+        }
+        _ => false,
+    }
+}
+
+/// Moves a pile onto the pile `distance` places to its left, closing the
+/// gap it leaves in the row.
+fn move_pile(piles: &mut Vec<Vec<Card>>, source: usize, distance: usize) -> bool {
+    if source < distance || !matches(piles, source, source - distance) {
+        return false;
+    }
+    let mut pile = piles.remove(source);
+    piles[source - distance].append(&mut pile);
+    true
+}
+
+fn game_over(piles: &[Vec<Card>]) -> bool {
+    for source in 1..piles.len() {
+        if matches(piles, source, source - 1) || (source >= 3 && matches(piles, source, source - 3))
+        {
+            return false;
+        }
+    }
+    true
+}
@@ -0,0 +1,553 @@
+use crate::card::Card;
+use crate::deck;
+use crate::moves::{self, Move, Position};
+use crate::rules::{Build, Empty, Foundation, Movable, Rules, Stock};
+use log::info;
+use std::process::exit;
+use std::time::{Duration, Instant};
+
+const CARDS: usize = 104;
+const PILES: usize = 32;
+const FACEUP: u8 = 0x80;
+const ID: u8 = 0x3F;
+/// Moves made in each deal by the benchmark.
+const WALK: usize = 200;
+
+/// A position packed into fixed arrays: every card is a byte holding its id,
+/// with the top bit set when it is face up, and the piles lie one after the
+/// other, each ending where `ends` says. Boards are copied instead of
+/// cloned, so making a move allocates nothing.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Board {
+    cards: [u8; CARDS],
+    ends: [u8; PILES],
+    piles: usize,
+    pub game: u64,
+    pub passes: usize,
+}
+
+impl Board {
+    pub fn from_position(position: &Position) -> Self {
+        if position.piles.len() > PILES {
+            println!("A board holds up to {} piles", PILES);
+            exit(1);
+        }
+        let mut board = Self {
+            cards: [0; CARDS],
+            ends: [0; PILES],
+            piles: position.piles.len(),
+            game: position.game,
+            passes: position.passes,
+        };
+        let mut end = 0;
+        for (i, pile) in position.piles.iter().enumerate() {
+            for card in pile {
+                if end == CARDS {
+                    println!("A board holds up to {} cards", CARDS);
+                    exit(1);
+                }
+                board.cards[end] = pack(card);
+                end += 1;
+            }
+            board.ends[i] = end as u8;
+        }
+        board
+    }
+
+    /// Unpacks the board back into piles of cards.
+    pub fn to_position(self) -> Position {
+        Position {
+            piles: (0..self.piles)
+                .map(|i| self.pile(i).iter().map(|b| unpack(*b)).collect())
+                .collect(),
+            game: self.game,
+            passes: self.passes,
+        }
+    }
+
+    pub fn pile(&self, index: usize) -> &[u8] {
+        &self.cards[self.start(index)..self.ends[index] as usize]
+    }
+
+    fn start(&self, index: usize) -> usize {
+        match index {
+            0 => 0,
+            _ => self.ends[index - 1] as usize,
+        }
+    }
+
+    fn len(&self, index: usize) -> usize {
+        self.ends[index] as usize - self.start(index)
+    }
+
+    fn top(&self, index: usize) -> Option<u8> {
+        self.pile(index).last().copied()
+    }
+
+    /// Every move the rules allow, in the order `moves::legal_moves` finds
+    /// them, into a buffer that is cleared first.
+    pub fn legal_moves(&self, rules: &Rules, found: &mut Vec<Move>) {
+        found.clear();
+        for source in 1..self.piles {
+            let pile = self.pile(source);
+            let mut quantity = 1;
+            while quantity <= pile.len() && faceup(pile[pile.len() - quantity]) {
+                if !self.can_move(rules, source, quantity) {
+                    break;
+                }
+                let card = pile[pile.len() - quantity];
+                for target in 2..self.piles {
+                    if self.accepts(rules, card, source, target, quantity) {
+                        found.push(Move::Cards {
+                            source,
+                            target,
+                            quantity,
+                        });
+                    }
+                }
+                quantity += 1;
+            }
+        }
+        if self.can_turn_stock(rules) {
+            found.push(Move::Stock);
+        }
+    }
+
+    /// Makes a move without checking it, like `moves::play`.
+    pub fn play(&mut self, rules: &Rules, next: &Move) {
+        match *next {
+            Move::Stock => {
+                self.turn_stock(rules);
+            }
+            Move::Cards {
+                source,
+                target,
+                quantity,
+            } => {
+                self.move_cards(source, target, quantity);
+                self.fill_from_reserve(rules);
+            }
+        }
+    }
+
+    fn can_turn_stock(&self, rules: &Rules) -> bool {
+        let hand = self.len(0) > 0;
+        let waste = self.len(1) > 0;
+        match rules.stock {
+            Stock::Waste => hand || (rules.recycle && waste),
+            Stock::Turn => hand && !waste,
+            Stock::Tableau => hand,
+            Stock::Redeal => self.passes < rules.redeals,
+            Stock::None => false,
+        }
+    }
+
+    fn turn_stock(&mut self, rules: &Rules) -> bool {
+        if !self.can_turn_stock(rules) {
+            return false;
+        }
+        match rules.stock {
+            Stock::Waste | Stock::Turn => {
+                let hand = self.ends[0] as usize;
+                let waste = self.ends[1] as usize;
+                if hand == 0 {
+                    self.passes += 1;
+                    self.cards[..waste].reverse();
+                    for card in self.cards[..waste].iter_mut() {
+                        *card &= ID;
+                    }
+                    self.ends[0] = waste as u8;
+                    return true;
+                }
+                let drawn = rules.draw.min(hand);
+                self.cards[hand - drawn..waste].rotate_left(drawn);
+                self.cards[waste - drawn..waste].reverse();
+                for card in self.cards[waste - drawn..waste].iter_mut() {
+                    *card |= FACEUP;
+                }
+                self.ends[0] -= drawn as u8;
+                true
+            }
+            Stock::Tableau => {
+                for i in rules.tableau_range() {
+                    if self.len(0) == 0 {
+                        break;
+                    }
+                    self.shift(0, i, 1);
+                    self.cards[self.ends[i] as usize - 1] |= FACEUP;
+                }
+                true
+            }
+            Stock::Redeal => {
+                self.redeal(rules);
+                true
+            }
+            Stock::None => false,
+        }
+    }
+
+    /// Shuffles the tableau cards where they lie and deals them again face
+    /// up, like `moves::redeal`.
+    fn redeal(&mut self, rules: &Rules) {
+        self.passes += 1;
+        let tableau = rules.tableau_range();
+        let start = self.start(tableau.start);
+        let end = self.ends[tableau.end - 1] as usize;
+        let cards = &mut self.cards[start..end];
+        deck::reshuffle_deck(cards, self.game, self.passes);
+        // The cards are dealt off the end of the shuffled ones.
+        cards.reverse();
+        for card in cards.iter_mut() {
+            *card |= FACEUP;
+        }
+        let fan = match rules.tableaus.first() {
+            Some((facedown, faceup)) => facedown + faceup,
+            None => exit(1),
+        };
+        for (k, i) in tableau.enumerate() {
+            self.ends[i] = (start + ((k + 1) * fan).min(end - start)) as u8;
+        }
+    }
+
+    /// Puts the top `quantity` cards of one pile on another and turns the
+    /// card left on top face up.
+    fn move_cards(&mut self, source: usize, target: usize, quantity: usize) {
+        self.shift(source, target, quantity);
+        if self.len(source) > 0 {
+            self.cards[self.ends[source] as usize - 1] |= FACEUP;
+        }
+    }
+
+    /// Moves cards between piles by rotating the piles in between.
+    fn shift(&mut self, source: usize, target: usize, quantity: usize) {
+        let q = quantity as u8;
+        if source < target {
+            let from = self.ends[source] as usize - quantity;
+            self.cards[from..self.ends[target] as usize].rotate_left(quantity);
+            for end in self.ends[source..target].iter_mut() {
+                *end -= q;
+            }
+        } else {
+            let from = self.ends[target] as usize;
+            self.cards[from..self.ends[source] as usize].rotate_right(quantity);
+            for end in self.ends[target..source].iter_mut() {
+                *end += q;
+            }
+        }
+    }
+
+    fn fill_from_reserve(&mut self, rules: &Rules) {
+        let reserve = match rules.reserve_index() {
+            Some(i) => i,
+            None => return,
+        };
+        for i in rules.tableau_range() {
+            if self.len(i) == 0 && self.len(reserve) > 0 {
+                self.move_cards(reserve, i, 1);
+            }
+        }
+    }
+
+    fn can_move(&self, rules: &Rules, source: usize, quantity: usize) -> bool {
+        if quantity == 1 {
+            return true;
+        }
+        if !rules.tableau_range().contains(&source) {
+            return false;
+        }
+        match rules.movable {
+            Movable::Single => false,
+            Movable::FaceUp => true,
+            Movable::Sequence => {
+                let pile = self.pile(source);
+                if pile.len() < quantity {
+                    return false;
+                }
+                if rules.cells > 0 && quantity > self.free_cells(rules) + 1 {
+                    return false;
+                }
+                pile[pile.len() - quantity..]
+                    .windows(2)
+                    .all(|w| builds_on(rules, w[1], w[0]))
+            }
+        }
+    }
+
+    fn accepts(
+        &self,
+        rules: &Rules,
+        card: u8,
+        source: usize,
+        target: usize,
+        quantity: usize,
+    ) -> bool {
+        if source == target {
+            return false;
+        }
+        let from_foundation = rules.foundation_range().contains(&source);
+        if from_foundation && !rules.from_foundations {
+            return false;
+        }
+        if rules.foundation_range().contains(&target) {
+            if quantity != 1 || from_foundation {
+                return false;
+            }
+            let pile = self.pile(target);
+            return match rules.foundation {
+                Foundation::InSuit => {
+                    pile.len() < 13
+                        && match pile.last() {
+                            Some(last) => {
+                                value(*last) % 13 + 1 == value(card) && suit(*last) == suit(card)
+                            }
+                            None => value(card) == rules.base,
+                        }
+                }
+                Foundation::Discard => self.can_discard(rules, card),
+                Foundation::Intervals => {
+                    let step = (target - rules.foundation_range().start + 1) as u8;
+                    pile.len() < 13
+                        && match pile.last() {
+                            Some(last) => (value(*last) + step - 1) % 13 + 1 == value(card),
+                            None => value(card) == step,
+                        }
+                }
+            };
+        }
+        if rules.tableau_range().contains(&target) {
+            if rules.build == Build::FromWaste {
+                return source == 1 && quantity == 1;
+            }
+            return match self.top(target) {
+                Some(last) => builds_on(rules, card, last),
+                None => match rules.empty {
+                    Empty::Kings => value(card) == 13,
+                    Empty::Any => true,
+                    Empty::None => false,
+                },
+            };
+        }
+        if rules.cell_range().contains(&target) {
+            return quantity == 1
+                && self.len(target) == 0
+                && !from_foundation
+                && !rules.cell_range().contains(&source);
+        }
+        false
+    }
+
+    fn can_discard(&self, rules: &Rules, card: u8) -> bool {
+        rules.tableau_range().any(|i| match self.top(i) {
+            Some(top) => suit(top) == suit(card) && rank(rules, top) > rank(rules, card),
+            None => false,
+        })
+    }
+
+    fn free_cells(&self, rules: &Rules) -> usize {
+        rules.cell_range().filter(|i| self.len(*i) == 0).count()
+    }
+}
+
+fn pack(card: &Card) -> u8 {
+    if card.is_faceup() {
+        return card.id() | FACEUP;
+    }
+    card.id()
+}
+
+fn unpack(byte: u8) -> Card {
+    let mut card = Card::from_id(byte & ID);
+    if faceup(byte) {
+        card.show();
+    }
+    card
+}
+
+fn faceup(byte: u8) -> bool {
+    byte & FACEUP != 0
+}
+
+fn value(byte: u8) -> u8 {
+    (byte & ID) % 13 + 1
+}
+
+fn suit(byte: u8) -> u8 {
+    (byte & ID) / 13
+}
+
+/// Hearts and diamonds, suits 0 and 3.
+fn red(byte: u8) -> bool {
+    matches!(suit(byte), 0 | 3)
+}
+
+fn rank(rules: &Rules, byte: u8) -> u8 {
+    if rules.aces_high && value(byte) == 1 {
+        return 14;
+    }
+    value(byte)
+}
+
+fn builds_on(rules: &Rules, card: u8, last: u8) -> bool {
+    let wrapped = rules.wrap && value(last) == 1 && value(card) == 13;
+    if value(last) != value(card) + 1 && !wrapped {
+        return false;
+    }
+    match rules.build {
+        Build::AlternateColors => red(last) != red(card),
+        Build::SameSuit => suit(last) == suit(card),
+        Build::AnySuit => true,
+        Build::FromWaste | Build::None => false,
+    }
+}
+
+/// Makes the same random moves from `deals` deals on the pile table and on
+/// the packed board, checking that both find the same moves, and times the
+/// move generation of each.
+pub fn benchmark(rules: &Rules, first: u64, deals: u64) {
+    let mut walks: Vec<(Position, Vec<Move>)> = Vec::with_capacity(deals as usize);
+    let mut found: Vec<Move> = Vec::new();
+    for game in first..first + deals {
+        let (rules, start) = Position::deal(rules, game);
+        let walk = moves::random_walk(&rules, &start, WALK);
+        let mut position = start.clone();
+        let mut board = Board::from_position(&start);
+        for (step, next) in walk.iter().enumerate() {
+            board.legal_moves(&rules, &mut found);
+            if moves::legal_moves(&rules, &position) != found {
+                println!("Game {}: the boards differ after {} moves", game, step);
+                exit(1);
+            }
+            moves::play(&rules, &mut position, next);
+            board.play(&rules, next);
+            if board != Board::from_position(&position) {
+                println!("Game {}: the boards differ after {} moves", game, step + 1);
+                exit(1);
+            }
+        }
+        walks.push((start, walk));
+    }
+
+    let mut generated = 0;
+    let start = Instant::now();
+    for (position, walk) in walks.iter() {
+        let mut position = position.clone();
+        for next in walk {
+            generated += moves::legal_moves(rules, &position).len();
+            let mut child = position.clone();
+            moves::play(rules, &mut child, next);
+            position = child;
+        }
+    }
+    let piles = start.elapsed();
+
+    let start = Instant::now();
+    for (position, walk) in walks.iter() {
+        let mut board = Board::from_position(position);
+        for next in walk {
+            board.legal_moves(rules, &mut found);
+            let mut child = board;
+            child.play(rules, next);
+            board = child;
+        }
+    }
+    let packed = start.elapsed();
+
+    let steps: usize = walks.iter().map(|(_, w)| w.len()).sum();
+    println!(
+        "Games {} to {}: {} positions, {} moves generated",
+        first,
+        first + deals - 1,
+        steps,
+        generated
+    );
+    println!(
+        "Pile table:   {:.3}s, {:.0} positions per second",
+        piles.as_secs_f64(),
+        per_second(steps, piles)
+    );
+    println!(
+        "Packed board: {:.3}s, {:.0} positions per second",
+        packed.as_secs_f64(),
+        per_second(steps, packed)
+    );
+    println!(
+        "Packed board is {:.1} times faster",
+        piles.as_secs_f64() / packed.as_secs_f64()
+    );
+    info!(
+        "Board benchmark from {}: {} positions, pile table {:?}, packed board {:?}",
+        first, steps, piles, packed
+    );
+}
+
+fn per_second(steps: usize, elapsed: Duration) -> f64 {
+    steps as f64 / elapsed.as_secs_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::GAMES;
+
+    #[test]
+    fn board_finds_and_plays_the_same_moves_as_the_pile_table() {
+        let mut found: Vec<Move> = Vec::new();
+        for name in GAMES.iter() {
+            for game in 1..=5 {
+                let (rules, mut position) = Position::deal(&Rules::from_name(name).unwrap(), game);
+                let mut board = Board::from_position(&position);
+                let walk = moves::random_walk(&rules, &position, WALK);
+                for (step, next) in walk.iter().enumerate() {
+                    board.legal_moves(&rules, &mut found);
+                    assert_eq!(
+                        moves::legal_moves(&rules, &position),
+                        found,
+                        "{} game {} move {}",
+                        name,
+                        game,
+                        step
+                    );
+                    moves::play(&rules, &mut position, next);
+                    board.play(&rules, next);
+                    assert_eq!(board, Board::from_position(&position));
+                    assert_eq!(board.to_position().piles, position.piles);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn to_position_gives_back_the_position() {
+        let (rules, mut position) = Position::deal(&Rules::klondike(), 4);
+        moves::play(&rules, &mut position, &Move::Stock);
+        position.passes = 2;
+        let unpacked = Board::from_position(&position).to_position();
+        assert_eq!(unpacked.piles, position.piles);
+        assert_eq!(unpacked.game, 4);
+        assert_eq!(unpacked.passes, 2);
+    }
+
+    #[test]
+    fn redeal_matches_the_pile_table() {
+        let (rules, mut position) = Position::deal(&Rules::la_belle_lucie(), 3);
+        let mut board = Board::from_position(&position);
+        for _ in 0..rules.redeals {
+            moves::play(&rules, &mut position, &Move::Stock);
+            board.play(&rules, &Move::Stock);
+            assert_eq!(board, Board::from_position(&position));
+        }
+        assert!(!board.can_turn_stock(&rules));
+    }
+
+    #[test]
+    fn moves_go_between_piles_on_either_side() {
+        let (_, position) = Position::deal(&Rules::klondike(), 1);
+        let mut board = Board::from_position(&position);
+        let top = board.top(8);
+        board.shift(8, 2, 1);
+        assert_eq!(board.top(2), top);
+        assert_eq!(board.len(8), 6);
+        board.shift(2, 8, 1);
+        assert_eq!(board, Board::from_position(&position));
+    }
+}
@@ -0,0 +1,293 @@
+use crate::board::Board;
+use crate::card::Card;
+use crate::moves::{self, Move, Position};
+use crate::rules::{Build, Empty, Goal, Rules, Stock};
+use log::info;
+use std::collections::HashSet;
+use std::process::exit;
+use std::time::Instant;
+
+/// Moves a game is given up after.
+const MAX_MOVES: usize = 2_000;
+
+/// How a game played by the bot ended.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Ending {
+    Won,
+    /// No move left that gets somewhere new.
+    Stuck,
+}
+
+/// Plays a deal to the end, making the move `choose` likes best each time.
+/// Returns how it ended and the moves made.
+pub fn play(rules: &Rules, game: u64) -> (Ending, usize) {
+    let (rules, position) = Position::deal(rules, game);
+    let mut board = Board::from_position(&position);
+    let mut seen: HashSet<Board> = HashSet::new();
+    seen.insert(seen_as(&rules, board));
+    for made in 0..MAX_MOVES {
+        let position = board.to_position();
+        if moves::game_over(&rules, &position.piles) {
+            return (Ending::Won, made);
+        }
+        let next = choose(&rules, &position).into_iter().find_map(|m| {
+            let mut after = board;
+            after.play(&rules, &m);
+            match seen.contains(&seen_as(&rules, after)) {
+                true => None,
+                false => Some(after),
+            }
+        });
+        match next {
+            Some(after) => {
+                board = after;
+                seen.insert(seen_as(&rules, board));
+            }
+            None => return (Ending::Stuck, made),
+        }
+    }
+    (Ending::Stuck, MAX_MOVES)
+}
+
+/// The board as the bot remembers it: like `Zobrist`, passes only tell
+/// boards apart when they are redeals.
+fn seen_as(rules: &Rules, mut board: Board) -> Board {
+    if rules.stock != Stock::Redeal {
+        board.passes = 0;
+    }
+    board
+}
+
+/// The moves the bot would make, best first: playing to the foundations,
+/// turning face down cards over, digging out cards the foundations take,
+/// freeing a card, bringing cards from the waste, reserve or cells into
+/// play, and turning the stock when nothing else is left. Moves that only
+/// shuffle cards around the tableau, take cards off the foundations or
+/// empty a pile with no king to fill it are left out.
+pub fn choose(rules: &Rules, position: &Position) -> Vec<Move> {
+    let mut scored: Vec<(u32, Move)> = moves::legal_moves(rules, position)
+        .into_iter()
+        .filter_map(|m| score(rules, position, &m).map(|s| (s, m)))
+        .collect();
+    scored.sort_by_key(|(s, _)| std::cmp::Reverse(*s));
+    scored.into_iter().map(|(_, m)| m).collect()
+}
+
+/// How much the bot likes a move, higher first, or None when it never
+/// makes it.
+pub fn score(rules: &Rules, position: &Position, next: &Move) -> Option<u32> {
+    let piles = &position.piles;
+    let (source, target, quantity) = match *next {
+        Move::Stock => return Some(1),
+        Move::Cards {
+            source,
+            target,
+            quantity,
+        } => (source, target, quantity),
+    };
+    if rules.foundation_range().contains(&source) {
+        return None;
+    }
+    let pile = &piles[source];
+    let left = pile.len() - quantity;
+    let hidden = pile[..left].iter().filter(|c| !c.is_faceup()).count() as u32;
+    let exposes = left > 0 && !pile[left - 1].is_faceup();
+    let tableau = rules.tableau_range().contains(&source);
+
+    if rules.foundation_range().contains(&target) {
+        return Some(if exposes { 200 + hidden } else { 150 });
+    }
+    if exposes {
+        return Some(100 + hidden);
+    }
+    let mut after = position.clone();
+    moves::play(rules, &mut after, next);
+    let dug = buried(rules, piles).saturating_sub(buried(rules, &after.piles));
+    if dug > 0 {
+        return Some(70 + dug);
+    }
+    if !tableau {
+        return Some(if rules.cell_range().contains(&target) {
+            5
+        } else {
+            50
+        });
+    }
+    if left > 0 {
+        if moves::builds_on(rules, &pile[left], &pile[left - 1]) {
+            return None;
+        }
+        return Some(60);
+    }
+    if piles[target].is_empty() {
+        return None;
+    }
+    if rules.empty == Empty::Kings && !king_waiting(rules, piles) {
+        return None;
+    }
+    Some(30)
+}
+
+/// How deep the cards the foundations take next lie in the tableau, as the
+/// number of cards on top of each of them added up.
+fn buried(rules: &Rules, piles: &[Vec<Card>]) -> u32 {
+    rules
+        .tableau_range()
+        .map(|i| {
+            let pile = &piles[i];
+            pile.iter()
+                .enumerate()
+                .filter(|(_, c)| {
+                    rules
+                        .foundation_range()
+                        .any(|f| moves::accepts(rules, piles, c, i, f, 1))
+                })
+                .map(|(d, _)| (pile.len() - 1 - d) as u32)
+                .sum::<u32>()
+        })
+        .sum()
+}
+
+/// Whether the bot knows the game: getting cards to the foundations by
+/// building down on the tableau. Games won with runs on the tableau or by
+/// choosing where to put each card turned aren't played that way.
+fn plays(rules: &Rules) -> bool {
+    rules.goal != Goal::Runs && rules.build != Build::FromWaste
+}
+
+/// Whether a face up king could be moved into an empty pile: the top of the
+/// waste or a king with cards under it on the tableau.
+fn king_waiting(rules: &Rules, piles: &[Vec<Card>]) -> bool {
+    let waste = matches!(piles[1].last(), Some(c) if c.get_value() == 13);
+    waste
+        || rules.tableau_range().any(|i| {
+            piles[i]
+                .iter()
+                .enumerate()
+                .any(|(d, c)| d > 0 && c.is_faceup() && c.get_value() == 13)
+        })
+}
+
+/// Plays the deals from `first` on and prints how many the bot won.
+pub fn start(rules: &Rules, first: u64, deals: u64) {
+    if !plays(rules) {
+        println!("The bot has no strategy for {}", rules.name);
+        exit(1);
+    }
+    let start = Instant::now();
+    let mut wins = 0;
+    let mut made = 0;
+    for game in first..first + deals {
+        let (result, count) = play(rules, game);
+        if result == Ending::Won {
+            wins += 1;
+        }
+        made += count;
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "Games {} to {}: {} won ({:.2}%), {:.0} moves a game, in {:.3}s, {:.0} deals per second",
+        first,
+        first + deals - 1,
+        wins,
+        100.0 * wins as f64 / deals as f64,
+        made as f64 / deals as f64,
+        elapsed.as_secs_f64(),
+        deals as f64 / elapsed.as_secs_f64()
+    );
+    info!(
+        "Bot from {}: {} of {} won in {:?}",
+        first, wins, deals, elapsed
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deck;
+
+    /// A face up card; suits in the order of `deck::new_deck`, hearts,
+    /// spades, diamonds and clubs.
+    fn card(suit: usize, value: usize) -> Card {
+        let mut card = deck::new_deck(1)[suit * 13 + value - 1];
+        card.show();
+        card
+    }
+
+    fn hidden(suit: usize, value: usize) -> Card {
+        deck::new_deck(1)[suit * 13 + value - 1]
+    }
+
+    fn empty(rules: &Rules) -> Position {
+        Position {
+            piles: vec![Vec::new(); rules.piles()],
+            game: 1,
+            passes: 0,
+        }
+    }
+
+    fn cards(source: usize, target: usize, quantity: usize) -> Move {
+        Move::Cards {
+            source,
+            target,
+            quantity,
+        }
+    }
+
+    #[test]
+    fn plays_to_the_foundations_first() {
+        let rules = Rules::klondike();
+        let mut position = empty(&rules);
+        position.piles[1] = vec![card(1, 5)];
+        position.piles[2] = vec![hidden(2, 9), card(0, 6)];
+        position.piles[3] = vec![card(0, 1)];
+        position.piles[0] = vec![hidden(3, 2)];
+        let chosen = choose(&rules, &position);
+        assert_eq!(chosen[0], cards(3, 9, 1));
+        assert_eq!(chosen.last(), Some(&Move::Stock));
+    }
+
+    #[test]
+    fn turns_a_card_over_before_playing_from_the_waste() {
+        let rules = Rules::klondike();
+        let mut position = empty(&rules);
+        position.piles[1] = vec![card(2, 7)];
+        position.piles[2] = vec![hidden(2, 9), card(1, 5)];
+        position.piles[3] = vec![card(0, 6)];
+        position.piles[4] = vec![card(1, 8)];
+        let chosen = choose(&rules, &position);
+        assert_eq!(chosen[0], cards(2, 3, 1));
+        assert_eq!(chosen[1], cards(1, 4, 1));
+    }
+
+    #[test]
+    fn never_takes_cards_off_the_foundations_or_splits_a_build() {
+        let rules = Rules::klondike();
+        let mut position = empty(&rules);
+        position.piles[9] = vec![card(3, 1), card(3, 2)];
+        position.piles[2] = vec![card(0, 3)];
+        position.piles[3] = vec![card(1, 9), card(0, 8)];
+        position.piles[4] = vec![card(3, 9)];
+        assert!(choose(&rules, &position).is_empty());
+    }
+
+    #[test]
+    fn digs_out_cards_the_foundations_take() {
+        let rules = Rules::bakers_dozen();
+        let mut position = empty(&rules);
+        position.piles[2] = vec![card(0, 1), card(1, 6), card(0, 5)];
+        position.piles[3] = vec![card(2, 7)];
+        position.piles[4] = vec![card(3, 6)];
+        position.piles[5] = vec![card(1, 9)];
+        let chosen = choose(&rules, &position);
+        assert_eq!(chosen[0], cards(2, 4, 1));
+    }
+
+    #[test]
+    fn leaves_out_games_it_has_no_strategy_for() {
+        assert!(plays(&Rules::klondike()));
+        assert!(plays(&Rules::aces_up()));
+        assert!(!plays(&Rules::scorpion()));
+        assert!(!plays(&Rules::calculation()));
+    }
+}
@@ -1,6 +1,6 @@
 use std::fmt;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum CardValue {
     A,
     N(u8),
@@ -23,7 +23,7 @@ impl fmt::Display for CardValue {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum CardType {
     Corazones,
     Espadas,
@@ -42,7 +42,7 @@ impl fmt::Display for CardType {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum CardColor {
     Red,
     Black,
@@ -57,7 +57,7 @@ impl fmt::Display for CardColor {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Card {
     suit: CardType,
     number: CardValue,
@@ -77,20 +77,18 @@ impl fmt::Display for Card {
 
 impl Card {
     pub fn new(suit: CardType, number: CardValue) -> Self {
-        let value: u8;
-        let color: CardColor;
-        match number {
-            CardValue::A => value = 1,
-            CardValue::N(i) => value = i,
-            CardValue::Z => value = 10,
-            CardValue::J => value = 11,
-            CardValue::Q => value = 12,
-            CardValue::K => value = 13,
-        }
-        match suit {
-            CardType::Corazones | CardType::Diamantes => color = CardColor::Red,
-            _ => color = CardColor::Black,
-        }
+        let value = match number {
+            CardValue::A => 1,
+            CardValue::N(i) => i,
+            CardValue::Z => 10,
+            CardValue::J => 11,
+            CardValue::Q => 12,
+            CardValue::K => 13,
+        };
+        let color = match suit {
+            CardType::Corazones | CardType::Diamantes => CardColor::Red,
+            _ => CardColor::Black,
+        };
         Self {
             suit,
             number,
@@ -111,6 +109,34 @@ impl Card {
     pub fn get_value(&self) -> u8 {
         self.value
     }
+    /// Number from 0 to 51 telling the cards of a deck apart.
+    pub fn id(&self) -> u8 {
+        let suit = match self.suit {
+            CardType::Corazones => 0,
+            CardType::Espadas => 1,
+            CardType::Treboles => 2,
+            CardType::Diamantes => 3,
+        };
+        suit * 13 + self.value - 1
+    }
+    /// The face down card `id` tells apart.
+    pub fn from_id(id: u8) -> Self {
+        let suit = match id / 13 {
+            0 => CardType::Corazones,
+            1 => CardType::Espadas,
+            2 => CardType::Treboles,
+            _ => CardType::Diamantes,
+        };
+        let number = match id % 13 + 1 {
+            1 => CardValue::A,
+            10 => CardValue::Z,
+            11 => CardValue::J,
+            12 => CardValue::Q,
+            13 => CardValue::K,
+            i => CardValue::N(i),
+        };
+        Self::new(suit, number)
+    }
     pub fn is_faceup(&self) -> bool {
         self.faceup
     }
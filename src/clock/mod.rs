@@ -0,0 +1,135 @@
+use crate::card::Card;
+use crate::deck;
+use log::info;
+use rand::Rng;
+use std::process::exit;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const KING: usize = 12;
+const DELAY: Duration = Duration::from_millis(150);
+/// Where each pile sits on the clock face, as (row, column) with the ace
+/// pile at one o'clock and the kings in the middle.
+const FACE: [(usize, usize); 13] = [
+    (1, 5),
+    (2, 6),
+    (3, 6),
+    (4, 6),
+    (5, 5),
+    (6, 3),
+    (5, 1),
+    (4, 0),
+    (3, 0),
+    (2, 0),
+    (1, 1),
+    (0, 3),
+    (3, 3),
+];
+
+pub fn start_game(mut game: u64, bench: u64) {
+    if bench > 0 {
+        benchmark(game.max(1), bench);
+        return;
+    }
+    if game == 0 {
+        let mut rng = rand::thread_rng();
+        game = rng.gen_range(1, 1000000);
+    }
+
+    let mut piles = set_up(game);
+    let mut current = KING;
+    info!("New Game! {}", game);
+    loop {
+        print!("\x1B[2J\x1B[1;1H");
+        print_clock(&piles, current);
+        match turn(&mut piles, current) {
+            Some(next) => current = next,
+            None => break,
+        }
+        thread::sleep(DELAY);
+    }
+
+    if won(&piles) {
+        println!("Game Over! All the cards were turned");
+        info!("Game Over! Won {}", game);
+    } else {
+        println!("Game Over! The fourth king came up too early");
+        info!("Game Over! Lost {}", game);
+    }
+}
+
+pub fn set_up(game: u64) -> Vec<Vec<Card>> {
+    let mut initial_deck = deck::new_deck(1);
+    deck::shuffle_deck(&mut initial_deck, game);
+    let mut piles: Vec<Vec<Card>> = (0..13).map(|_| Vec::with_capacity(4)).collect();
+    for i in 0..52 {
+        match initial_deck.pop() {
+            Some(c) => piles[i % 13].push(c),
+            None => exit(1),
+        }
+    }
+    piles
+}
+
+/// Turns the top card of the current pile and puts it face up under the
+/// pile of its rank. Returns that pile, or None when the current pile has
+/// no face down cards left.
+fn turn(piles: &mut [Vec<Card>], current: usize) -> Option<usize> {
+    match piles[current].last() {
+        Some(c) if !c.is_faceup() => {}
+        _ => return None,
+    }
+    let mut card = piles[current].pop()?;
+    card.show();
+    let next = card.get_value() as usize - 1;
+    piles[next].insert(0, card);
+    Some(next)
+}
+
+fn won(piles: &[Vec<Card>]) -> bool {
+    piles.iter().flatten().all(|c| c.is_faceup())
+}
+
+/// Plays a whole deal without printing it.
+pub fn play(game: u64) -> bool {
+    let mut piles = set_up(game);
+    let mut current = KING;
+    while let Some(next) = turn(&mut piles, current) {
+        current = next;
+    }
+    won(&piles)
+}
+
+fn benchmark(first: u64, deals: u64) {
+    let start = Instant::now();
+    let wins = (first..first + deals).filter(|game| play(*game)).count();
+    let elapsed = start.elapsed();
+    println!(
+        "Games {} to {}: {} won ({:.2}%) in {:.3}s, {:.0} deals per second",
+        first,
+        first + deals - 1,
+        wins,
+        100.0 * wins as f64 / deals as f64,
+        elapsed.as_secs_f64(),
+        deals as f64 / elapsed.as_secs_f64()
+    );
+    info!(
+        "Clock benchmark from {}: {} of {} won in {:?}",
+        first, wins, deals, elapsed
+    );
+}
+
+fn print_clock(piles: &[Vec<Card>], current: usize) {
+    let mut grid: Vec<Vec<String>> = vec![vec![String::from("    "); 7]; 7];
+    for (i, (row, column)) in FACE.iter().enumerate() {
+        let card = match piles[i].iter().find(|c| c.is_faceup()) {
+            Some(c) => format!("{}", c),
+            None => String::from("XXX"),
+        };
+        let marker = if i == current { "<" } else { " " };
+        grid[*row][*column] = format!("{}{}", card, marker);
+    }
+    for row in grid {
+        println!("{}", row.concat().trim_end());
+    }
+}
@@ -1,15 +1,19 @@
 use crate::card::{Card, CardType, CardValue};
+use crate::rules::{Foundation, Rules, Stock};
 use rand::seq::SliceRandom;
 use rand::{rngs::StdRng, SeedableRng};
+use std::ops::Range;
 use std::process::exit;
 
-pub fn new_deck() -> Vec<Card> {
-    let mut deck: Vec<Card> = Vec::with_capacity(52);
-    load_suit(&mut deck, CardType::Corazones);
-    load_suit(&mut deck, CardType::Espadas);
-    load_suit(&mut deck, CardType::Diamantes);
-    load_suit(&mut deck, CardType::Treboles);
-    return deck;
+pub fn new_deck(decks: usize) -> Vec<Card> {
+    let mut deck: Vec<Card> = Vec::with_capacity(52 * decks);
+    for _ in 0..decks {
+        load_suit(&mut deck, CardType::Corazones);
+        load_suit(&mut deck, CardType::Espadas);
+        load_suit(&mut deck, CardType::Diamantes);
+        load_suit(&mut deck, CardType::Treboles);
+    }
+    deck
 }
 
 fn load_suit(deck: &mut Vec<Card>, suit: CardType) {
@@ -29,44 +33,103 @@ fn load_suit(deck: &mut Vec<Card>, suit: CardType) {
     deck.push(k_card);
 }
 
-pub fn shuffle_deck(deck: &mut Vec<Card>, game: u64) {
+pub fn shuffle_deck<T>(deck: &mut [T], game: u64) {
     let mut rng = StdRng::seed_from_u64(game);
     deck.shuffle(&mut rng);
 }
 
-pub fn set_up(game: u64) -> Vec<Vec<Card>> {
-    let mut piles: Vec<Vec<Card>> = Vec::with_capacity(13);
-    let mut initial_deck = new_deck();
+/// Shuffles cards gathered up during a game. Every pass gets its own seed
+/// derived from the deal, so a game always redeals the same way.
+pub fn reshuffle_deck<T>(deck: &mut [T], game: u64, pass: usize) {
+    shuffle_deck(deck, game ^ ((pass as u64) << 32));
+}
+
+/// Deals a game. Games with a dealt base card get their `base` rank set here.
+pub fn set_up(rules: &mut Rules, game: u64) -> Vec<Vec<Card>> {
+    let mut piles: Vec<Vec<Card>> = Vec::with_capacity(rules.piles());
+    let mut initial_deck = new_deck(rules.decks);
     shuffle_deck(&mut initial_deck, game);
 
-    create_tableaus(&mut piles, &mut initial_deck);
+    let reserve = create_tableau(
+        rules.reserve.saturating_sub(1),
+        rules.reserve.min(1),
+        &mut initial_deck,
+    );
+
+    let mut foundations: Vec<Vec<Card>> = (0..rules.foundations)
+        .map(|_| Vec::with_capacity(13))
+        .collect();
+    if rules.base_card {
+        match initial_deck.pop() {
+            Some(mut c) => {
+                c.show();
+                rules.base = c.get_value();
+                foundations[0].push(c);
+            }
+            None => exit(1),
+        }
+    }
+    if rules.foundation == Foundation::Intervals {
+        deal_intervals(&mut foundations, &mut initial_deck);
+    }
+
+    create_tableaus(rules, &mut piles, &mut initial_deck);
+
+    let mut cells: Vec<Vec<Card>> = Vec::with_capacity(rules.cells);
+    for _ in 0..rules.cells {
+        cells.push(create_tableau(
+            0,
+            initial_deck.len().min(1),
+            &mut initial_deck,
+        ));
+    }
 
     let empty_deck: Vec<Card> = Vec::new();
 
     piles.insert(0, initial_deck);
-    piles.insert(1, empty_deck.clone());
+    piles.insert(1, empty_deck);
+
+    piles.append(&mut foundations);
+    piles.append(&mut cells);
 
-    let empty_foundation: Vec<Card> = Vec::with_capacity(13);
-    for _ in 0..4 {
-        piles.push(empty_foundation.clone());
+    if rules.reserve > 0 {
+        piles.push(reserve);
     }
 
     piles
 }
 
-fn create_tableaus(piles: &mut Vec<Vec<Card>>, deck: &mut Vec<Card>) {
-    for i in 1..8 {
-        piles.push(create_tableau(i, deck));
+/// Deals each foundation, counted from 1, the first card of that rank left
+/// in the deck.
+fn deal_intervals(foundations: &mut [Vec<Card>], deck: &mut Vec<Card>) {
+    for (i, foundation) in foundations.iter_mut().enumerate() {
+        match deck.iter().rposition(|c| c.get_value() as usize == i + 1) {
+            Some(position) => {
+                let mut card = deck.remove(position);
+                card.show();
+                foundation.push(card);
+            }
+            None => exit(1),
+        }
+    }
+}
+
+fn create_tableaus(rules: &Rules, piles: &mut Vec<Vec<Card>>, deck: &mut Vec<Card>) {
+    for (facedown, faceup) in rules.tableaus.iter() {
+        let mut tableau = create_tableau(*facedown, *faceup, deck);
+        if rules.kings_to_bottom {
+            tableau.sort_by_key(|c| !matches!(c.get_card_value(), CardValue::K));
+        }
+        piles.push(tableau);
     }
 }
 
-fn create_tableau(size: u8, deck: &mut Vec<Card>) -> Vec<Card> {
+fn create_tableau(facedown: usize, faceup: usize, deck: &mut Vec<Card>) -> Vec<Card> {
     let mut tableau: Vec<Card> = Vec::with_capacity(13);
-    for i in 0..size {
-        let card = deck.pop();
-        match card {
+    for i in 0..facedown + faceup {
+        match deck.pop() {
             Some(mut x) => {
-                if i == size - 1 {
+                if i >= facedown {
                     x.show();
                 }
                 tableau.push(x)
@@ -77,43 +140,53 @@ fn create_tableau(size: u8, deck: &mut Vec<Card>) -> Vec<Card> {
     tableau
 }
 
-pub fn print_piles(piles: &Vec<Vec<Card>>) {
-    print_top(piles);
-    let largest = largest_tableau(piles);
-    print_tableaus(piles, largest);
+pub fn print_piles(rules: &Rules, piles: &[Vec<Card>]) {
+    print_top(rules, piles);
+    let largest = largest_tableau(piles, rules.tableau_range());
+    print_tableaus(piles, rules.tableau_range(), largest);
     println!();
 }
 
-fn print_top(piles: &Vec<Vec<Card>>) {
-    print_last(piles, 0);
-    print_last(piles, 1);
-    print!("    ");
-    print_last(piles, 9);
-    print_last(piles, 10);
-    print_last(piles, 11);
-    print_last(piles, 12);
+fn print_top(rules: &Rules, piles: &[Vec<Card>]) {
+    match rules.stock {
+        Stock::Waste | Stock::Turn => {
+            print_last(piles, 0);
+            print_last(piles, 1);
+            if let Some(i) = rules.reserve_index() {
+                print_last(piles, i);
+            }
+            print!("    ");
+        }
+        Stock::Tableau => {
+            print_last(piles, 0);
+            print!("    ");
+        }
+        Stock::Redeal | Stock::None => {}
+    }
+    for i in rules.foundation_range() {
+        print_last(piles, i);
+    }
+    if rules.cells > 0 {
+        println!();
+        for i in rules.cell_range() {
+            print_last(piles, i);
+        }
+    }
 }
 
-fn print_last(piles: &Vec<Vec<Card>>, index: usize) {
+pub fn print_last(piles: &[Vec<Card>], index: usize) {
     match piles.get(index) {
-        Some(pile) => {
-            let size = pile.len();
-            if size == 0 {
-                print!("___ ");
-                return;
-            }
-            match pile.get(size - 1) {
-                Some(card) => print!("{} ", card),
-                None => exit(1),
-            }
-        }
+        Some(pile) => match pile.last() {
+            Some(card) => print!("{} ", card),
+            None => print!("___ "),
+        },
         None => exit(1),
     }
 }
 
-fn largest_tableau(piles: &Vec<Vec<Card>>) -> usize {
+pub fn largest_tableau(piles: &[Vec<Card>], tableaus: Range<usize>) -> usize {
     let mut max = 0;
-    for i in 2..9 {
+    for i in tableaus {
         match piles.get(i) {
             Some(pile) => {
                 let temp = pile.len();
@@ -127,10 +200,10 @@ fn largest_tableau(piles: &Vec<Vec<Card>>) -> usize {
     max
 }
 
-fn print_tableaus(piles: &Vec<Vec<Card>>, largest: usize) {
+pub fn print_tableaus(piles: &[Vec<Card>], tableaus: Range<usize>, largest: usize) {
     for n in 0..largest {
         println!();
-        for i in 2..9 {
+        for i in tableaus.clone() {
             match piles.get(i) {
                 Some(pile) => match pile.get(n) {
                     Some(card) => print!("{} ", card),
@@ -141,3 +214,23 @@ fn print_tableaus(piles: &Vec<Vec<Card>>, largest: usize) {
         }
     }
 }
+
+/// Prints the top card of piles laid out in a single row, wrapped every
+/// `width` piles, with the pile at `cursor` marked underneath.
+pub fn print_row(piles: &[Vec<Card>], width: usize, cursor: usize) {
+    for (n, line) in piles.chunks(width).enumerate() {
+        for pile in line {
+            match pile.last() {
+                Some(card) => print!("{} ", card),
+                None => print!("___ "),
+            }
+        }
+        println!();
+        let start = n * width;
+        if (start..start + line.len()).contains(&cursor) {
+            println!("{}^^^", "    ".repeat(cursor - start));
+        } else {
+            println!();
+        }
+    }
+}
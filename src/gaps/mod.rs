@@ -0,0 +1,306 @@
+use crate::card::Card;
+use crate::deck;
+use crate::input::{read_key, Add};
+use crossterm::event::KeyCode;
+use log::info;
+use rand::Rng;
+use std::process::exit;
+use undo::Record;
+
+const COLUMNS: usize = 13;
+const SLOTS: usize = 52;
+const RESHUFFLES: usize = 3;
+const KEYS: &str = "1234";
+
+#[derive(Debug, Clone)]
+struct Table {
+    /// Four rows of 13 slots, read left to right and top to bottom. The
+    /// slots left by the aces are the gaps.
+    grid: Vec<Option<Card>>,
+    game: u64,
+    /// Reshuffles done so far.
+    passes: usize,
+}
+
+pub fn start_game(mut game: u64) {
+    if game == 0 {
+        let mut rng = rand::thread_rng();
+        game = rng.gen_range(1, 1000000);
+    }
+    let mut table = set_up(game);
+    let mut record = Record::default();
+    match record.apply(Add(table.clone())) {
+        Ok(_) => {}
+        Err(_) => exit(1),
+    }
+
+    print!("\x1B[2J\x1B[1;1H");
+    print_table(&table);
+    info!("New Game!{}", table_to_log(&table));
+
+    loop {
+        if won(&table) {
+            println!("Game Over! Every suit is in order");
+            info!("Game Over! Won{}", table_to_log(&table));
+            return;
+        }
+        if stuck(&table) {
+            if table.passes == RESHUFFLES {
+                println!("Game Over! Cards in place: {}", in_place(&table));
+                info!(
+                    "Game Over! Cards in place: {}{}",
+                    in_place(&table),
+                    table_to_log(&table)
+                );
+                return;
+            }
+            println!("No moves left, press RET to reshuffle");
+        }
+        let key = read_key();
+        print!("\x1B[2J\x1B[1;1H");
+        match key {
+            KeyCode::Enter => {
+                if reshuffle(&mut table) {
+                    match record.apply(Add(table.clone())) {
+                        Ok(_) => {}
+                        Err(_) => exit(1),
+                    }
+                    info!("Reshuffle {}{}", table.passes, table_to_log(&table));
+                } else {
+                    println!("No reshuffles left");
+                }
+            }
+            KeyCode::Char('u') | KeyCode::Char('U') => {
+                if record.current() > 1 {
+                    match record.undo() {
+                        Ok(_) => {}
+                        Err(_) => exit(1),
+                    }
+                    restore(&mut table, &record);
+                    info!("Undo{}", table_to_log(&table));
+                }
+            }
+            KeyCode::Char('i') | KeyCode::Char('I') => {
+                if record.current() < record.len() {
+                    match record.redo() {
+                        Ok(_) => {}
+                        Err(_) => exit(1),
+                    }
+                    restore(&mut table, &record);
+                    info!("Redo{}", table_to_log(&table));
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') => {
+                start_game(0);
+                return;
+            }
+            KeyCode::Esc => return,
+            KeyCode::Char(c) => match KEYS.find(c).and_then(|i| gaps(&table).get(i).copied()) {
+                Some(gap) => {
+                    if fill(&mut table, gap) {
+                        match record.apply(Add(table.clone())) {
+                            Ok(_) => {}
+                            Err(_) => exit(1),
+                        }
+                        info!("Gap {} filled{}", c, table_to_log(&table));
+                    } else {
+                        println!("No moves");
+                    }
+                }
+                None => println!("Invalid command"),
+            },
+            _ => println!("Invalid command"),
+        }
+        print_table(&table);
+    }
+}
+
+/// Deals the whole deck face up and takes the aces out.
+fn set_up(game: u64) -> Table {
+    let mut initial_deck = deck::new_deck(1);
+    deck::shuffle_deck(&mut initial_deck, game);
+    let grid = initial_deck
+        .into_iter()
+        .map(|mut c| {
+            c.show();
+            if c.get_value() == 1 {
+                return None;
+            }
+            Some(c)
+        })
+        .collect();
+    Table {
+        grid,
+        game,
+        passes: 0,
+    }
+}
+
+fn restore(table: &mut Table, record: &Record<Vec<Table>>) {
+    match record.target().get(record.current() - 1) {
+        Some(t) => *table = t.clone(),
+        None => exit(1),
+    }
+}
+
+fn gaps(table: &Table) -> Vec<usize> {
+    (0..SLOTS).filter(|s| table.grid[*s].is_none()).collect()
+}
+
+/// Slots holding a card that can be moved into a gap: the card of the same
+/// suit one rank higher than the card on its left, or any two out of the
+/// first column when the gap starts a row.
+fn fits(table: &Table, gap: usize) -> Vec<usize> {
+    if gap % COLUMNS == 0 {
+        return (0..SLOTS)
+            .filter(|s| match table.grid[*s] {
+                Some(c) => c.get_value() == 2 && *s % COLUMNS != 0,
+                None => false,
+            })
+            .collect();
+    }
+    let left = match table.grid[gap - 1] {
+        Some(c) => c,
+        None => return Vec::new(),
+    };
+    (0..SLOTS)
+        .filter(|s| match table.grid[*s] {
+            Some(c) => {
+                c.get_card_suit() == left.get_card_suit() && c.get_value() == left.get_value() + 1
+            }
+            None => false,
+        })
+        .collect()
+}
+
+/// Fills a gap, asking which two to use when more than one fits.
+fn fill(table: &mut Table, gap: usize) -> bool {
+    let sources = fits(table, gap);
+    let source = match sources.len() {
+        0 => return false,
+        1 => sources[0],
+        _ => match choose(table, &sources) {
+            Some(s) => s,
+            None => return false,
+        },
+    };
+    table.grid.swap(source, gap);
+    true
+}
+
+fn choose(table: &Table, sources: &[usize]) -> Option<usize> {
+    let mut choices = String::from("");
+    for (i, source) in sources.iter().enumerate() {
+        if let (Some(k), Some(c)) = (KEYS.chars().nth(i), table.grid[*source]) {
+            choices.push_str(&format!(" {}: {}", k, c));
+        }
+    }
+    print_table(table);
+    println!("Which card?{}", choices);
+    let key = read_key();
+    print!("\x1B[2J\x1B[1;1H");
+    match key {
+        KeyCode::Char(c) => KEYS.find(c).and_then(|i| sources.get(i).copied()),
+        _ => None,
+    }
+}
+
+/// Cards of a row already in order from the two in its first slot.
+fn row_in_place(table: &Table, row: usize) -> usize {
+    let start = row * COLUMNS;
+    let first = match table.grid[start] {
+        Some(c) if c.get_value() == 2 => c,
+        _ => return 0,
+    };
+    let mut count = 1;
+    while count < COLUMNS - 1 {
+        match table.grid[start + count] {
+            Some(c)
+                if c.get_card_suit() == first.get_card_suit()
+                    && c.get_value() as usize == count + 2 => {}
+            _ => break,
+        }
+        count += 1;
+    }
+    count
+}
+
+fn in_place(table: &Table) -> usize {
+    (0..SLOTS / COLUMNS)
+        .map(|row| row_in_place(table, row))
+        .sum()
+}
+
+fn won(table: &Table) -> bool {
+    in_place(table) == SLOTS / COLUMNS * (COLUMNS - 1)
+}
+
+fn stuck(table: &Table) -> bool {
+    gaps(table).iter().all(|gap| fits(table, *gap).is_empty())
+}
+
+/// Gathers every card not yet in place and deals them again after the cards
+/// in place of each row, leaving the gap right after those.
+fn reshuffle(table: &mut Table) -> bool {
+    if table.passes >= RESHUFFLES {
+        return false;
+    }
+    table.passes += 1;
+    let rows: Vec<usize> = (0..SLOTS / COLUMNS)
+        .map(|row| row_in_place(table, row))
+        .collect();
+    let mut cards: Vec<Card> = Vec::with_capacity(SLOTS);
+    for (row, placed) in rows.iter().enumerate() {
+        for column in *placed..COLUMNS {
+            if let Some(c) = table.grid[row * COLUMNS + column].take() {
+                cards.push(c);
+            }
+        }
+    }
+    deck::reshuffle_deck(&mut cards, table.game, table.passes);
+    for (row, placed) in rows.iter().enumerate() {
+        for column in placed + 1..COLUMNS {
+            match cards.pop() {
+                Some(c) => table.grid[row * COLUMNS + column] = Some(c),
+                None => exit(1),
+            }
+        }
+    }
+    true
+}
+
+fn print_table(table: &Table) {
+    println!(
+        "Reshuffles left: {}    In place: {}",
+        RESHUFFLES - table.passes,
+        in_place(table)
+    );
+    let gaps = gaps(table);
+    for (row, line) in table.grid.chunks(COLUMNS).enumerate() {
+        println!();
+        for (column, slot) in line.iter().enumerate() {
+            match slot {
+                Some(c) => print!("{} ", c),
+                None => match gaps.iter().position(|g| *g == row * COLUMNS + column) {
+                    Some(i) => print!("_{}_ ", i + 1),
+                    None => exit(1),
+                },
+            }
+        }
+    }
+    println!();
+}
+
+fn table_to_log(table: &Table) -> String {
+    let mut log_string = String::from("");
+    for line in table.grid.chunks(COLUMNS) {
+        log_string.push('\n');
+        for slot in line {
+            match slot {
+                Some(c) => c.log(&mut log_string),
+                None => log_string.push_str("___ "),
+            }
+        }
+    }
+    log_string
+}
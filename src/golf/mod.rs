@@ -0,0 +1,243 @@
+use crate::card::Card;
+use crate::deck;
+use crate::input::{read_key, Add};
+use crate::logger;
+use crate::moves;
+use crossterm::event::KeyCode;
+use log::info;
+use rand::Rng;
+use std::process::exit;
+use undo::Record;
+
+const HOLES: u64 = 9;
+const COLUMNS: usize = 7;
+const COLUMN_SIZE: usize = 5;
+
+pub fn start_game(mut game: u64) {
+    if game == 0 {
+        let mut rng = rand::thread_rng();
+        game = rng.gen_range(1, 1000000);
+    }
+
+    let mut scores: Vec<i32> = Vec::with_capacity(HOLES as usize);
+    for hole in 0..HOLES {
+        match play_hole(game + hole, hole + 1) {
+            Some(score) => scores.push(score),
+            None => return,
+        }
+        print_scores(&scores);
+        if scores.len() < HOLES as usize {
+            println!("Press any key to play the next hole");
+            if read_key() == KeyCode::Esc {
+                return;
+            }
+        }
+    }
+
+    let total: i32 = scores.iter().sum();
+    println!("Game Over! Total score: {}", total);
+    info!("Game Over! Total score: {}", total);
+}
+
+pub fn set_up(game: u64) -> Vec<Vec<Card>> {
+    let mut piles: Vec<Vec<Card>> = Vec::with_capacity(COLUMNS + 2);
+    let mut initial_deck = deck::new_deck(1);
+    deck::shuffle_deck(&mut initial_deck, game);
+
+    for _ in 0..COLUMNS {
+        let mut column: Vec<Card> = Vec::with_capacity(COLUMN_SIZE);
+        for _ in 0..COLUMN_SIZE {
+            match initial_deck.pop() {
+                Some(mut c) => {
+                    c.show();
+                    column.push(c);
+                }
+                None => exit(1),
+            }
+        }
+        piles.push(column);
+    }
+
+    piles.insert(0, initial_deck);
+    piles.insert(1, Vec::with_capacity(52));
+    moves::get_hand(&mut piles, 1, false);
+
+    piles
+}
+
+fn play_hole(game: u64, hole: u64) -> Option<i32> {
+    let mut piles = set_up(game);
+    let mut record = Record::default();
+    match record.apply(Add(piles.clone())) {
+        Ok(_) => {}
+        Err(_) => exit(1),
+    }
+
+    print!("\x1B[2J\x1B[1;1H");
+    println!("Hole {} (game {})", hole, game);
+    print_piles(&piles);
+    info!("New Hole {}!\n{}", hole, piles_to_log(&piles));
+
+    loop {
+        if hole_over(&piles) {
+            let score = score(&piles);
+            println!("Hole {} finished with score {}", hole, score);
+            info!("Hole {} finished with score {}", hole, score);
+            return Some(score);
+        }
+        let key = read_key();
+        print!("\x1B[2J\x1B[1;1H");
+        println!("Hole {} (game {})", hole, game);
+        match key {
+            KeyCode::Char(c) if ('1'..='7').contains(&c) => {
+                let column = c as usize - '1' as usize + 2;
+                if play_column(&mut piles, column) {
+                    match record.apply(Add(piles.clone())) {
+                        Ok(_) => {}
+                        Err(_) => exit(1),
+                    }
+                }
+            }
+            KeyCode::Enter => {
+                if stock_size(&piles) > 0 {
+                    moves::get_hand(&mut piles, 1, false);
+                    match record.apply(Add(piles.clone())) {
+                        Ok(_) => {}
+                        Err(_) => exit(1),
+                    }
+                    info!("From Hand to Foundation\n{}", piles_to_log(&piles));
+                } else {
+                    println!("No cards left in the stock");
+                }
+            }
+            KeyCode::Char('u') | KeyCode::Char('U') => {
+                if record.current() > 1 {
+                    match record.undo() {
+                        Ok(_) => {}
+                        Err(_) => exit(1),
+                    }
+                    restore(&mut piles, &record);
+                    info!("Undo\n{}", piles_to_log(&piles));
+                }
+            }
+            KeyCode::Char('i') | KeyCode::Char('I') => {
+                if record.current() < record.len() {
+                    match record.redo() {
+                        Ok(_) => {}
+                        Err(_) => exit(1),
+                    }
+                    restore(&mut piles, &record);
+                    info!("Redo\n{}", piles_to_log(&piles));
+                }
+            }
+            KeyCode::Esc => return None,
+            _ => println!("Invalid command"),
+        }
+        print_piles(&piles);
+    }
+}
+
+fn restore(piles: &mut Vec<Vec<Card>>, record: &Record<Vec<Vec<Vec<Card>>>>) {
+    match record.target().get(record.current() - 1) {
+        Some(r) => *piles = r.clone(),
+        None => exit(1),
+    }
+}
+
+fn play_column(piles: &mut [Vec<Card>], column: usize) -> bool {
+    let card = match moves::get_user_card(piles, column, 1) {
+        Some(c) => c,
+        None => {
+            println!("No moves");
+            return false;
+        }
+    };
+    if !valid_move(&card, &piles[1]) {
+        info!("No moves for {}\n{}", column - 1, piles_to_log(piles));
+        println!("No moves");
+        return false;
+    }
+    moves::move_card(piles, column, 1, 1);
+    info!("From {} to Foundation\n{}", column - 1, piles_to_log(piles));
+    true
+}
+
+/// A card can be played on the foundation when it is one rank above or
+/// below its top card, regardless of suit.
+fn valid_move(card: &Card, foundation: &[Card]) -> bool {
+    match foundation.last() {
+        Some(top) => {
+            let (a, b) = (top.get_value(), card.get_value());
+            a + 1 == b || b + 1 == a
+        }
+        None => true,
+    }
+}
+
+fn stock_size(piles: &[Vec<Card>]) -> usize {
+    match piles.first() {
+        Some(p) => p.len(),
+        None => exit(1),
+    }
+}
+
+fn tableau_size(piles: &[Vec<Card>]) -> usize {
+    piles.iter().skip(2).map(|p| p.len()).sum()
+}
+
+fn hole_over(piles: &[Vec<Card>]) -> bool {
+    if tableau_size(piles) == 0 {
+        return true;
+    }
+    if stock_size(piles) > 0 {
+        return false;
+    }
+    for column in piles.iter().skip(2) {
+        if let Some(card) = column.last() {
+            if valid_move(card, &piles[1]) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Golf scoring: one stroke per card left on the tableau, or minus one per
+/// card left in the stock when the tableau was cleared.
+fn score(piles: &[Vec<Card>]) -> i32 {
+    let left = tableau_size(piles);
+    if left > 0 {
+        return left as i32;
+    }
+    -(stock_size(piles) as i32)
+}
+
+fn print_scores(scores: &[i32]) {
+    print!("Holes:");
+    for hole in 1..=scores.len() {
+        print!(" {:>3}", hole);
+    }
+    println!();
+    print!("Score:");
+    for score in scores {
+        print!(" {:>3}", score);
+    }
+    println!("  Total: {}", scores.iter().sum::<i32>());
+}
+
+fn print_piles(piles: &[Vec<Card>]) {
+    deck::print_last(piles, 0);
+    deck::print_last(piles, 1);
+    let largest = deck::largest_tableau(piles, 2..COLUMNS + 2);
+    deck::print_tableaus(piles, 2..COLUMNS + 2, largest);
+    println!();
+}
+
+fn piles_to_log(piles: &[Vec<Card>]) -> String {
+    let mut log_string = String::from("");
+    logger::last_to_log(piles, 0, &mut log_string);
+    logger::last_to_log(piles, 1, &mut log_string);
+    let largest = logger::largest_tableau(piles, 2..COLUMNS + 2);
+    logger::tableaus_to_log(piles, 2..COLUMNS + 2, largest, &mut log_string);
+    log_string
+}
@@ -1,91 +1,213 @@
+use crate::accordion;
+use crate::board;
+use crate::bot;
 use crate::card::Card;
+use crate::clock;
 use crate::deck;
+use crate::gaps;
+use crate::golf;
 use crate::logger;
-use crate::moves;
+use crate::moves::{self, Move, Position};
+use crate::rating;
+use crate::rules::{Difficulty, Rules, Stock};
+use crate::solver::{self, Limits, Outcome};
+use crate::tripeaks;
 use crossterm::event::{read, Event, KeyCode, KeyEvent};
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
 use log::info;
 use rand::Rng;
 use std::env;
+use std::fmt::Debug;
 use std::process::exit;
+use std::time::Duration;
 use undo::{Command, Record};
 
+/// Records a snapshot of the game, which undoing puts back.
 #[derive(Debug)]
-struct Add(Vec<Vec<Card>>);
+pub struct Add<T>(pub T);
 
-impl Command<Vec<Vec<Vec<Card>>>> for Add {
-    fn apply(&mut self, s: &mut Vec<Vec<Vec<Card>>>) -> undo::Result {
+impl<T: Clone + Debug + 'static> Command<Vec<T>> for Add<T> {
+    fn apply(&mut self, s: &mut Vec<T>) -> undo::Result {
         s.push(self.0.clone());
         Ok(())
     }
 
-    fn undo(&mut self, s: &mut Vec<Vec<Vec<Card>>>) -> undo::Result {
+    fn undo(&mut self, s: &mut Vec<T>) -> undo::Result {
         self.0 = s.pop().ok_or("s is empty")?;
         Ok(())
     }
 }
 
+/// What the player did during a game.
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub moves: u32,
+    pub undos: u32,
+    pub hints: u32,
+}
+
 pub fn start_game() {
-    let args: Vec<String> = env::args().collect();
+    let mut args = env::args().skip(1);
     let mut game: u64 = 0;
-    if args.len() > 1 {
-        match args[1].parse::<u64>() {
-            Ok(n) => game = n,
-            Err(_) => {}
+    let mut variant = String::from("klondike");
+    let mut wrap = false;
+    let mut bench: u64 = 0;
+    let mut draw: Option<u64> = None;
+    let mut command = String::from("play");
+    let mut nodes = solver::NODES;
+    let mut time: Option<u64> = None;
+    let mut winnable = false;
+    let mut deals: u64 = 1000;
+    let mut out: Option<String> = None;
+    let mut difficulty: Option<Difficulty> = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--wrap" => wrap = true,
+            "--bench" => bench = number_arg(&arg, args.next()),
+            "--draw" => draw = Some(number_arg(&arg, args.next())),
+            "--nodes" => nodes = number_arg(&arg, args.next()),
+            "--time" => time = Some(number_arg(&arg, args.next())),
+            "--winnable" => winnable = true,
+            "--deals" => deals = number_arg(&arg, args.next()),
+            "--out" => match args.next() {
+                Some(file_name) => out = Some(file_name),
+                None => {
+                    println!("--out expects a file name");
+                    exit(1);
+                }
+            },
+            "--difficulty" => match args.next().and_then(|d| Difficulty::from_name(&d)) {
+                Some(d) => difficulty = Some(d),
+                None => {
+                    println!("--difficulty expects easy, medium or hard");
+                    exit(1);
+                }
+            },
+            "solve" | "batch" | "bench" | "rate" | "bot" => command = arg,
+            _ => match arg.parse::<u64>() {
+                Ok(n) => game = n,
+                Err(_) => variant = arg.to_lowercase(),
+            },
         }
     }
-    new_game(game);
+    let play = command == "play";
+    match variant.as_str() {
+        "golf" | "tripeaks" | "accordion" | "clock" | "gaps" if draw.is_some() => no_draw(),
+        "golf" if play => golf::start_game(game),
+        "tripeaks" if play => tripeaks::start_game(game, wrap),
+        "accordion" if play => accordion::start_game(game),
+        "clock" if play => clock::start_game(game, bench),
+        "gaps" if play => gaps::start_game(game),
+        "golf" | "tripeaks" | "accordion" | "clock" | "gaps" => {
+            println!(
+                "{} only works for games played on the pile table, not {}",
+                command, variant
+            );
+            exit(1);
+        }
+        _ => match Rules::from_name(&variant) {
+            Some(mut rules) => {
+                if let Some(d) = draw {
+                    if rules.stock != Stock::Waste {
+                        no_draw();
+                    }
+                    rules.draw = d.max(1) as usize;
+                }
+                if winnable {
+                    let budget = time.unwrap_or(solver::WINNABLE_TIME);
+                    rules.winnable = Some(Duration::from_secs(budget));
+                }
+                rules.difficulty = difficulty;
+                let limits = Limits {
+                    nodes,
+                    time: Duration::from_secs(time.unwrap_or(solver::TIME)),
+                };
+                match command.as_str() {
+                    "solve" => solver::start(&rules, game, limits),
+                    "rate" => rating::start(&rules, game),
+                    "bot" => bot::start(&rules, game.max(1), deals.max(1)),
+                    "bench" => board::benchmark(&rules, game.max(1), deals.max(1)),
+                    "batch" => {
+                        let default = format!("{}_{}.csv", rules.name, game.max(1));
+                        let file_name = out.unwrap_or(default);
+                        solver::batch(&rules, game.max(1), deals.max(1), limits, &file_name)
+                    }
+                    _ => new_game(&rules, game),
+                }
+            }
+            None => {
+                println!("Unknown game: {}", variant);
+                exit(1);
+            }
+        },
+    }
 }
 
-fn new_game(mut game: u64) {
-    if game == 0 {
-        let mut rng = rand::thread_rng();
-        game = rng.gen_range(1, 1000000);
+fn no_draw() {
+    println!("--draw only works in games with a talon");
+    exit(1);
+}
+
+fn number_arg(option: &str, value: Option<String>) -> u64 {
+    match value.map(|v| v.parse::<u64>()) {
+        Some(Ok(n)) => n,
+        _ => {
+            println!("{} expects a number", option);
+            exit(1);
+        }
     }
-    let mut piles = deck::set_up(game);
+}
+
+fn new_game(rules: &Rules, game: u64) {
+    let (game, rating) = match (game, rules.difficulty, rules.winnable) {
+        (0, Some(d), _) => rating::pick_game(rules, d),
+        (0, None, Some(_)) => {
+            let game = solver::winnable_game(rules);
+            (game, rating::rate(rules, game))
+        }
+        (0, None, None) => {
+            let game = rand::thread_rng().gen_range(1, 1000000);
+            (game, rating::rate(rules, game))
+        }
+        _ => (game, rating::rate(rules, game)),
+    };
+    let (rules, mut position) = Position::deal(rules, game);
+    let rules = &rules;
     print!("\x1B[2J\x1B[1;1H");
-    deck::print_piles(&piles);
+    deck::print_piles(rules, &position.piles);
+    match (rating.winnable, rating.difficulty(), rating.score) {
+        (Some(false), _, _) => println!("Game {}: unwinnable", game),
+        (_, Some(d), Some(score)) => println!("Game {}: {} ({}/100)", game, d.name(), score),
+        _ => println!("Game {}: unrated", game),
+    }
     let mut record = Record::default();
-    match record.apply(Add(piles.clone())) {
+    match record.apply(Add(position.clone())) {
         Ok(_) => {}
         Err(_) => exit(1),
     }
 
-    info!("New Game!\n{}", logger::piles_to_log(&piles));
+    info!(
+        "New Game!\n{}",
+        logger::piles_to_log(rules, &position.piles)
+    );
+    info!("Rated {:?}", rating);
 
-    actions(&mut piles, &mut record);
+    actions(rules, &mut position, &mut record, &mut Stats::default());
 }
 
-fn game_over(piles: &Vec<Vec<Card>>) -> bool {
-    for i in 9..13 {
-        match piles.get(i) {
-            Some(p) => {
-                if p.len() < 13 {
-                    return false;
-                }
-            }
-            None => exit(1),
-        }
-    }
-    true
-}
-
-fn user_move(piles: &mut Vec<Vec<Card>>, piles_index: usize) -> bool {
+fn user_move(rules: &Rules, piles: &mut [Vec<Card>], piles_index: usize) -> bool {
     let mut pile_index = 1;
     loop {
-        let user_card = moves::get_user_card(&piles, piles_index, pile_index);
+        let user_card = moves::get_user_card(piles, piles_index, pile_index);
         match user_card {
             Some(c) => {
-                let target_pile = moves::valid_moves(&piles, &c, piles_index);
+                if !moves::can_move(rules, piles, piles_index, pile_index) {
+                    return no_moves(rules, piles, piles_index);
+                }
+                let target_pile = moves::valid_moves(rules, piles, &c, piles_index, pile_index);
                 if target_pile == 0 {
-                    if piles_index == 1 || piles_index > 8 {
-                        info!(
-                            "No moves for {}\n{}",
-                            piles_index_to_str(piles_index),
-                            logger::piles_to_log(&piles)
-                        );
-                        println!("No moves");
-                        return false;
+                    if !rules.tableau_range().contains(&piles_index) {
+                        return no_moves(rules, piles, piles_index);
                     }
                     pile_index += 1;
                     continue;
@@ -93,135 +215,190 @@ fn user_move(piles: &mut Vec<Vec<Card>>, piles_index: usize) -> bool {
                 if moves::move_card(piles, piles_index, target_pile, pile_index) {
                     info!(
                         "From {} to {}\n{}",
-                        piles_index_to_str(piles_index),
-                        piles_index_to_str(target_pile),
-                        logger::piles_to_log(&piles)
+                        rules.index_to_str(piles_index),
+                        rules.index_to_str(target_pile),
+                        logger::piles_to_log(rules, piles)
                     );
                     return true;
                 }
                 pile_index += 1;
             }
             None => {
-                info!(
-                    "No moves for {}\n{}",
-                    piles_index_to_str(piles_index),
-                    logger::piles_to_log(&piles)
-                );
-                println!("No moves");
-                return false;
+                return no_moves(rules, piles, piles_index);
             }
         }
     }
 }
 
-fn piles_index_to_str(index: usize) -> String {
-    match index {
-        0 => String::from("Hand"),
-        1 => String::from("Waste"),
-        2 => String::from("1"),
-        3 => String::from("2"),
-        4 => String::from("3"),
-        5 => String::from("4"),
-        6 => String::from("5"),
-        7 => String::from("6"),
-        8 => String::from("7"),
-        9 => String::from("q"),
-        10 => String::from("w"),
-        11 => String::from("e"),
-        12 => String::from("r"),
-        _ => exit(1),
+/// Moves as many cards from the top of a pile as it takes for the target
+/// pile to take them.
+fn user_move_to(rules: &Rules, piles: &mut [Vec<Card>], piles_index: usize, target: usize) -> bool {
+    let mut pile_index = 1;
+    while let Some(c) = moves::get_user_card(piles, piles_index, pile_index) {
+        if moves::can_move(rules, piles, piles_index, pile_index)
+            && moves::accepts(rules, piles, &c, piles_index, target, pile_index)
+        {
+            moves::move_card(piles, piles_index, target, pile_index);
+            info!(
+                "From {} to {}\n{}",
+                rules.index_to_str(piles_index),
+                rules.index_to_str(target),
+                logger::piles_to_log(rules, piles)
+            );
+            return true;
+        }
+        pile_index += 1;
     }
+    info!(
+        "No moves from {} to {}\n{}",
+        rules.index_to_str(piles_index),
+        rules.index_to_str(target),
+        logger::piles_to_log(rules, piles)
+    );
+    println!("No moves");
+    false
 }
 
-pub fn actions(piles: &mut Vec<Vec<Card>>, record: &mut Record<Vec<Vec<Vec<Card>>>>) {
+/// Falls back to putting the top card of the pile in a free cell when it
+/// has nowhere else to go.
+fn no_moves(rules: &Rules, piles: &mut [Vec<Card>], piles_index: usize) -> bool {
+    let cell = moves::free_cell(rules, piles, piles_index);
+    if cell != 0 && moves::get_user_card(piles, piles_index, 1).is_some() {
+        moves::move_card(piles, piles_index, cell, 1);
+        info!(
+            "From {} to {}\n{}",
+            rules.index_to_str(piles_index),
+            rules.index_to_str(cell),
+            logger::piles_to_log(rules, piles)
+        );
+        return true;
+    }
+    info!(
+        "No moves for {}\n{}",
+        rules.index_to_str(piles_index),
+        logger::piles_to_log(rules, piles)
+    );
+    println!("No moves");
+    false
+}
+
+pub fn actions(
+    rules: &Rules,
+    position: &mut Position,
+    record: &mut Record<Vec<Position>>,
+    stats: &mut Stats,
+) {
+    let mut hints: Vec<Move> = Vec::new();
+    let mut hint = 0;
     loop {
-        if game_over(piles) {
-            println!("Game Over!");
-            info!("Game Over!\n{}", logger::piles_to_log(&piles));
+        if moves::game_over(rules, &position.piles) {
+            println!(
+                "Game Over! Moves: {}  Undos: {}  Hints: {}",
+                stats.moves, stats.undos, stats.hints
+            );
+            info!(
+                "Game Over! {:?}\n{}",
+                stats,
+                logger::piles_to_log(rules, &position.piles)
+            );
             return;
         }
+        if solver::dead_end(rules, position) {
+            println!("No more moves, the game is lost! Press u to undo, BS to restart or n for a new deal");
+            info!("No more moves");
+        }
         let key = read_key();
         print!("\x1B[2J\x1B[1;1H");
+        if key != KeyCode::Tab {
+            hints.clear();
+        }
         match key {
-            KeyCode::Char('h') => {
-                add_record(piles, 1, record);
-            }
-            KeyCode::Char('1') => {
-                add_record(piles, 2, record);
-            }
-            KeyCode::Char('2') => {
-                add_record(piles, 3, record);
-            }
-            KeyCode::Char('3') => {
-                add_record(piles, 4, record);
-            }
-            KeyCode::Char('4') => {
-                add_record(piles, 5, record);
-            }
-            KeyCode::Char('5') => {
-                add_record(piles, 6, record);
-            }
-            KeyCode::Char('6') => {
-                add_record(piles, 7, record);
-            }
-            KeyCode::Char('7') => {
-                add_record(piles, 8, record);
-            }
-            KeyCode::Char('q') => {
-                add_record(piles, 9, record);
-            }
-            KeyCode::Char('w') => {
-                add_record(piles, 10, record);
-            }
-            KeyCode::Char('e') => {
-                add_record(piles, 11, record);
+            KeyCode::Char('?') => {
+                deck::print_piles(rules, &position.piles);
+                println!("Looking for a solution...");
+                let from_deal = match record.target().first() {
+                    Some(p) => p.clone(),
+                    None => {
+                        println!("The deal is missing from the game record");
+                        exit(1);
+                    }
+                };
+                let solution = match solver::solve(rules, position, solver::REPLAY_LIMITS).outcome {
+                    Outcome::Won(path) => Some((position.clone(), path, "the current position")),
+                    _ if from_deal.piles == position.piles => None,
+                    _ => match solver::solve(rules, &from_deal, solver::REPLAY_DEAL_LIMITS).outcome
+                    {
+                        Outcome::Won(path) => Some((from_deal, path, "the deal")),
+                        _ => None,
+                    },
+                };
+                print!("\x1B[2J\x1B[1;1H");
+                match solution {
+                    Some((start, path, from)) => {
+                        info!("Replay from {}: {} moves", from, path.len());
+                        replay(rules, &start, &path, from);
+                        print!("\x1B[2J\x1B[1;1H");
+                        deck::print_piles(rules, &position.piles);
+                    }
+                    None => {
+                        deck::print_piles(rules, &position.piles);
+                        println!("No solution found");
+                    }
+                }
             }
-            KeyCode::Char('r') => {
-                add_record(piles, 12, record);
+            KeyCode::Tab => {
+                if hints.is_empty() {
+                    hints = solver::hints(rules, position);
+                    hint = 0;
+                } else {
+                    hint = (hint + 1) % hints.len();
+                }
+                stats.hints += 1;
+                deck::print_piles(rules, &position.piles);
+                match hints.get(hint) {
+                    Some(m) => {
+                        println!(
+                            "Hint {} of {}: {}",
+                            hint + 1,
+                            hints.len(),
+                            m.describe(rules)
+                        );
+                        info!("Hint: {}", m.describe(rules));
+                    }
+                    None => println!("No moves"),
+                }
             }
-            KeyCode::Enter => {
-                moves::get_hand(piles);
-                match record.apply(Add(piles.clone())) {
-                    Ok(_) => {}
-                    Err(_) => exit(1),
+            KeyCode::Enter if rules.stock != Stock::None => {
+                if moves::turn_stock(rules, position) {
+                    stats.moves += 1;
+                    match record.apply(Add(position.clone())) {
+                        Ok(_) => {}
+                        Err(_) => exit(1),
+                    }
+                    info!(
+                        "{}\n{}",
+                        Move::Stock.describe(rules),
+                        logger::piles_to_log(rules, &position.piles)
+                    );
+                } else if rules.stock == Stock::Redeal {
+                    println!("No redeals left");
+                } else if rules.stock == Stock::Turn && !position.piles[1].is_empty() {
+                    println!("Play the card on the waste first");
+                } else {
+                    println!("No cards left in the stock");
                 }
-                info!(
-                    "From {} to {}\n{}",
-                    piles_index_to_str(0),
-                    piles_index_to_str(1),
-                    logger::piles_to_log(&piles)
-                );
-                deck::print_piles(piles);
+                deck::print_piles(rules, &position.piles);
             }
             KeyCode::Esc => break,
-            KeyCode::Char('n') => {
-                new_game(0);
+            KeyCode::Char('n') | KeyCode::Char('N') => {
+                new_game(rules, 0);
                 break;
             }
-            KeyCode::Char('N') => {
-                new_game(0);
-                break;
-            }
-            KeyCode::Char('u') => {
-                if record.current() < 2 {
-                    continue;
-                }
-                match record.undo() {
-                    Ok(_) => {}
-                    Err(_) => exit(1),
-                }
-                match record.target().get(record.current() - 1) {
-                    Some(r) => {
-                        let mut record_piles = r.clone();
-                        info!("Undo\n{}", logger::piles_to_log(&record_piles));
-                        deck::print_piles(&record_piles);
-                        actions(&mut record_piles, record)
-                    }
-                    None => exit(1),
-                }
+            KeyCode::Backspace => {
+                new_game(rules, position.game);
                 break;
             }
-            KeyCode::Char('U') => {
+            KeyCode::Char('u') | KeyCode::Char('U') => {
                 if record.current() < 2 {
                     continue;
                 }
@@ -229,18 +406,22 @@ pub fn actions(piles: &mut Vec<Vec<Card>>, record: &mut Record<Vec<Vec<Vec<Card>
                     Ok(_) => {}
                     Err(_) => exit(1),
                 }
+                stats.undos += 1;
                 match record.target().get(record.current() - 1) {
                     Some(r) => {
-                        let mut record_piles = r.clone();
-                        info!("Undo\n{}", logger::piles_to_log(&record_piles));
-                        deck::print_piles(&record_piles);
-                        actions(&mut record_piles, record)
+                        let mut record_position = r.clone();
+                        info!(
+                            "Undo\n{}",
+                            logger::piles_to_log(rules, &record_position.piles)
+                        );
+                        deck::print_piles(rules, &record_position.piles);
+                        actions(rules, &mut record_position, record, stats)
                     }
                     None => exit(1),
                 }
                 break;
             }
-            KeyCode::Char('i') => {
+            KeyCode::Char('i') | KeyCode::Char('I') => {
                 if record.current() == record.len() {
                     continue;
                 }
@@ -250,47 +431,90 @@ pub fn actions(piles: &mut Vec<Vec<Card>>, record: &mut Record<Vec<Vec<Vec<Card>
                 }
                 match record.target().get(record.current() - 1) {
                     Some(r) => {
-                        let mut record_piles = r.clone();
-                        info!("Redo\n{}", logger::piles_to_log(&record_piles));
-                        deck::print_piles(&record_piles);
-                        actions(&mut record_piles, record)
+                        let mut record_position = r.clone();
+                        info!(
+                            "Redo\n{}",
+                            logger::piles_to_log(rules, &record_position.piles)
+                        );
+                        deck::print_piles(rules, &record_position.piles);
+                        actions(rules, &mut record_position, record, stats)
                     }
                     None => exit(1),
                 }
                 break;
             }
-            KeyCode::Char('I') => {
-                match record.redo() {
-                    Ok(_) => {}
-                    Err(_) => exit(1),
-                }
-                match record.target().get(record.current() - 1) {
-                    Some(r) => {
-                        let mut record_piles = r.clone();
-                        info!("Redo\n{}", logger::piles_to_log(&record_piles));
-                        deck::print_piles(&record_piles);
-                        actions(&mut record_piles, record)
+            KeyCode::Char(' ') => {
+                deck::print_piles(rules, &position.piles);
+                println!("Move from?");
+                let source = read_pile(rules);
+                println!("To?");
+                let target = read_pile(rules);
+                print!("\x1B[2J\x1B[1;1H");
+                match (source, target) {
+                    (Some(s), Some(t)) => add_record(rules, position, s, Some(t), record, stats),
+                    _ => {
+                        println!("Invalid command");
+                        deck::print_piles(rules, &position.piles);
                     }
-                    None => exit(1),
                 }
-                break;
             }
+            KeyCode::Char(c) => match rules.key_to_index(c) {
+                Some(piles_index) => add_record(rules, position, piles_index, None, record, stats),
+                None => {
+                    println!("Invalid command");
+                    deck::print_piles(rules, &position.piles);
+                }
+            },
             _ => {
                 println!("Invalid command");
-                deck::print_piles(piles);
+                deck::print_piles(rules, &position.piles);
             }
         }
     }
 }
 
-fn read_key() -> KeyCode {
+/// Steps through a solution on the board, one move at a time, until ESC.
+fn replay(rules: &Rules, start: &Position, path: &[Move], from: &str) {
+    let mut positions: Vec<Position> = Vec::with_capacity(path.len() + 1);
+    positions.push(start.clone());
+    for next in path {
+        let mut position = match positions.last() {
+            Some(p) => p.clone(),
+            None => exit(1),
+        };
+        moves::play(rules, &mut position, next);
+        positions.push(position);
+    }
+    let mut step = 0;
+    loop {
+        deck::print_piles(rules, &positions[step].piles);
+        println!("Solution from {}, move {} of {}", from, step, path.len());
+        if step > 0 {
+            println!("Made: {}", path[step - 1].describe(rules));
+        }
+        match path.get(step) {
+            Some(m) => println!("Next: {}", m.describe(rules)),
+            None => println!("Won!"),
+        }
+        println!("LEFT/RIGHT: Step back/forward  ESC: Back to the game");
+        let key = read_key();
+        print!("\x1B[2J\x1B[1;1H");
+        match key {
+            KeyCode::Right if step < path.len() => step += 1,
+            KeyCode::Left if step > 0 => step -= 1,
+            KeyCode::Esc => return,
+            _ => {}
+        }
+    }
+}
+
+pub fn read_key() -> KeyCode {
     let mut user_input = KeyCode::Null;
     //going into raw mode
     enable_raw_mode().unwrap();
     //matching the key
-    match read().unwrap() {
-        Event::Key(KeyEvent { code, .. }) => user_input = code,
-        _ => {}
+    if let Event::Key(KeyEvent { code, .. }) = read().unwrap() {
+        user_input = code;
     }
 
     //disabling raw mode
@@ -299,16 +523,34 @@ fn read_key() -> KeyCode {
     user_input
 }
 
+fn read_pile(rules: &Rules) -> Option<usize> {
+    match read_key() {
+        KeyCode::Char(c) => rules.key_to_index(c),
+        _ => None,
+    }
+}
+
+/// Makes a move from a pile, to the given target or else to the first pile
+/// that takes it.
 fn add_record(
-    piles: &mut Vec<Vec<Card>>,
+    rules: &Rules,
+    position: &mut Position,
     piles_index: usize,
-    record: &mut Record<Vec<Vec<Vec<Card>>>>,
+    target: Option<usize>,
+    record: &mut Record<Vec<Position>>,
+    stats: &mut Stats,
 ) {
-    if user_move(piles, piles_index) {
-        match record.apply(Add(piles.clone())) {
+    let moved = match target {
+        Some(t) => user_move_to(rules, &mut position.piles, piles_index, t),
+        None => user_move(rules, &mut position.piles, piles_index),
+    };
+    if moved {
+        stats.moves += 1;
+        moves::fill_from_reserve(rules, &mut position.piles);
+        match record.apply(Add(position.clone())) {
             Ok(_) => {}
             Err(_) => exit(1),
         }
     }
-    deck::print_piles(piles);
+    deck::print_piles(rules, &position.piles);
 }
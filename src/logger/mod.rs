@@ -1,18 +1,17 @@
 use crate::card::Card;
+use crate::rules::{Rules, Stock};
 use chrono::prelude::*;
 use simplelog::*;
 use std::fs;
 use std::fs::File;
+use std::ops::Range;
 use std::process::exit;
 
 pub fn init() {
     fs::create_dir_all("logs").unwrap();
 
     let dt = Utc::now();
-    let file_name = format!(
-        "logs/solitaire_{}.log",
-        dt.format("%Y-%m-%d_%H:%M:%S").to_string()
-    );
+    let file_name = format!("logs/solitaire_{}.log", dt.format("%Y-%m-%d_%H:%M:%S"));
 
     let mut config = simplelog::ConfigBuilder::new();
 
@@ -24,44 +23,54 @@ pub fn init() {
     .unwrap();
 }
 
-pub fn piles_to_log(piles: &Vec<Vec<Card>>) -> String {
+pub fn piles_to_log(rules: &Rules, piles: &[Vec<Card>]) -> String {
     let mut log_string = String::from("");
-    top_to_log(piles, &mut log_string);
-    let largest = largest_tableau(piles);
-    tableaus_to_log(piles, largest, &mut log_string);
-    return log_string;
+    top_to_log(rules, piles, &mut log_string);
+    let largest = largest_tableau(piles, rules.tableau_range());
+    tableaus_to_log(piles, rules.tableau_range(), largest, &mut log_string);
+    log_string
 }
 
-fn top_to_log(piles: &Vec<Vec<Card>>, log_string: &mut String) {
-    last_to_log(piles, 0, log_string);
-    last_to_log(piles, 1, log_string);
-    log_string.push_str("    ");
-    last_to_log(piles, 9, log_string);
-    last_to_log(piles, 10, log_string);
-    last_to_log(piles, 11, log_string);
-    last_to_log(piles, 12, log_string);
+fn top_to_log(rules: &Rules, piles: &[Vec<Card>], log_string: &mut String) {
+    match rules.stock {
+        Stock::Waste | Stock::Turn => {
+            last_to_log(piles, 0, log_string);
+            last_to_log(piles, 1, log_string);
+            if let Some(i) = rules.reserve_index() {
+                last_to_log(piles, i, log_string);
+            }
+            log_string.push_str("    ");
+        }
+        Stock::Tableau => {
+            last_to_log(piles, 0, log_string);
+            log_string.push_str("    ");
+        }
+        Stock::Redeal | Stock::None => {}
+    }
+    for i in rules.foundation_range() {
+        last_to_log(piles, i, log_string);
+    }
+    if rules.cells > 0 {
+        log_string.push('\n');
+        for i in rules.cell_range() {
+            last_to_log(piles, i, log_string);
+        }
+    }
 }
 
-fn last_to_log(piles: &Vec<Vec<Card>>, index: usize, log_string: &mut String) {
+pub fn last_to_log(piles: &[Vec<Card>], index: usize, log_string: &mut String) {
     match piles.get(index) {
-        Some(pile) => {
-            let size = pile.len();
-            if size == 0 {
-                log_string.push_str("___ ");
-                return;
-            }
-            match pile.get(size - 1) {
-                Some(card) => card.log(log_string),
-                None => exit(1),
-            }
-        }
+        Some(pile) => match pile.last() {
+            Some(card) => card.log(log_string),
+            None => log_string.push_str("___ "),
+        },
         None => exit(1),
     }
 }
 
-fn largest_tableau(piles: &Vec<Vec<Card>>) -> usize {
+pub fn largest_tableau(piles: &[Vec<Card>], tableaus: Range<usize>) -> usize {
     let mut max = 0;
-    for i in 2..9 {
+    for i in tableaus {
         match piles.get(i) {
             Some(pile) => {
                 let temp = pile.len();
@@ -75,10 +84,15 @@ fn largest_tableau(piles: &Vec<Vec<Card>>) -> usize {
     max
 }
 
-fn tableaus_to_log(piles: &Vec<Vec<Card>>, largest: usize, log_string: &mut String) {
+pub fn tableaus_to_log(
+    piles: &[Vec<Card>],
+    tableaus: Range<usize>,
+    largest: usize,
+    log_string: &mut String,
+) {
     for n in 0..largest {
-        log_string.push_str("\n");
-        for i in 2..9 {
+        log_string.push('\n');
+        for i in tableaus.clone() {
             match piles.get(i) {
                 Some(pile) => match pile.get(n) {
                     Some(card) => card.log(log_string),
@@ -89,3 +103,17 @@ fn tableaus_to_log(piles: &Vec<Vec<Card>>, largest: usize, log_string: &mut Stri
         }
     }
 }
+
+pub fn row_to_log(piles: &[Vec<Card>], width: usize) -> String {
+    let mut log_string = String::from("");
+    for line in piles.chunks(width) {
+        log_string.push('\n');
+        for pile in line {
+            match pile.last() {
+                Some(card) => card.log(&mut log_string),
+                None => log_string.push_str("___ "),
+            }
+        }
+    }
+    log_string
+}
@@ -1,8 +1,19 @@
+mod accordion;
+mod board;
+mod bot;
 mod card;
+mod clock;
 mod deck;
+mod gaps;
+mod golf;
 mod input;
 mod logger;
 mod moves;
+mod rating;
+mod rules;
+mod solver;
+mod tripeaks;
+mod zobrist;
 
 fn main() {
     logger::init();
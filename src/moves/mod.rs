@@ -1,68 +1,277 @@
-use crate::card::{Card, CardColor, CardType, CardValue};
+use crate::card::{Card, CardValue};
+use crate::deck;
+use crate::rules::{Build, Empty, Foundation, Goal, Movable, Rules, Stock};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use std::process::exit;
 
-pub fn get_hand(piles: &mut Vec<Vec<Card>>) {
-    let hand: &mut Vec<Card>;
-    let mut waste: &mut Vec<Card>;
+/// A game in progress on the pile table.
+#[derive(Debug, Clone)]
+pub struct Position {
+    pub piles: Vec<Vec<Card>>,
+    /// Number of the deal, which also seeds the redeals.
+    pub game: u64,
+    /// Times the waste has been turned over or the tableau redealt.
+    pub passes: usize,
+}
 
-    match piles.get_mut(0) {
-        Some(p) => hand = p,
-        None => exit(1),
+impl Position {
+    /// Deals the given game, returning the rules as the deal leaves them.
+    pub fn deal(rules: &Rules, game: u64) -> (Rules, Position) {
+        let mut rules = rules.clone();
+        let piles = deck::set_up(&mut rules, game);
+        let position = Position {
+            piles,
+            game,
+            passes: 0,
+        };
+        (rules, position)
+    }
+}
+
+/// A move on the pile table, as a player makes it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Move {
+    /// Pressing RET: turning or dealing the stock, or redealing.
+    Stock,
+    /// The top `quantity` cards of one pile put on another.
+    Cards {
+        source: usize,
+        target: usize,
+        quantity: usize,
+    },
+}
+
+impl Move {
+    /// Describes the move the way the log does.
+    pub fn describe(&self, rules: &Rules) -> String {
+        match *self {
+            Move::Stock => match rules.stock {
+                Stock::Tableau => format!("From {} to Tableau", rules.index_to_str(0)),
+                Stock::Redeal => String::from("Redeal"),
+                _ => format!(
+                    "From {} to {}",
+                    rules.index_to_str(0),
+                    rules.index_to_str(1)
+                ),
+            },
+            Move::Cards { source, target, .. } => format!(
+                "From {} to {}",
+                rules.index_to_str(source),
+                rules.index_to_str(target)
+            ),
+        }
+    }
+}
+
+/// Every move the rules allow in a position.
+pub fn legal_moves(rules: &Rules, position: &Position) -> Vec<Move> {
+    let piles = &position.piles;
+    let mut moves: Vec<Move> = Vec::new();
+    for source in 1..rules.piles() {
+        let mut quantity = 1;
+        while let Some(card) = get_user_card(piles, source, quantity) {
+            if !can_move(rules, piles, source, quantity) {
+                break;
+            }
+            for target in 2..rules.piles() {
+                if accepts(rules, piles, &card, source, target, quantity) {
+                    moves.push(Move::Cards {
+                        source,
+                        target,
+                        quantity,
+                    });
+                }
+            }
+            quantity += 1;
+        }
+    }
+    if can_turn_stock(rules, position) {
+        moves.push(Move::Stock);
+    }
+    moves
+}
+
+/// Up to `steps` legal moves from `start`, each picked at random, seeded by
+/// the number of the deal so the same walk is made every time.
+pub fn random_walk(rules: &Rules, start: &Position, steps: usize) -> Vec<Move> {
+    let mut rng = StdRng::seed_from_u64(start.game);
+    let mut position = start.clone();
+    let mut walk: Vec<Move> = Vec::with_capacity(steps);
+    for _ in 0..steps {
+        let legal = legal_moves(rules, &position);
+        if legal.is_empty() {
+            break;
+        }
+        let next = legal[rng.gen_range(0, legal.len())];
+        play(rules, &mut position, &next);
+        walk.push(next);
+    }
+    walk
+}
+
+pub fn can_turn_stock(rules: &Rules, position: &Position) -> bool {
+    let hand = !position.piles[0].is_empty();
+    let waste = !position.piles[1].is_empty();
+    match rules.stock {
+        Stock::Waste => hand || (rules.recycle && waste),
+        Stock::Turn => hand && !waste,
+        Stock::Tableau => hand,
+        Stock::Redeal => position.passes < rules.redeals,
+        Stock::None => false,
+    }
+}
+
+/// Makes a move without checking it, filling empty piles from the reserve
+/// afterwards like the game does.
+pub fn play(rules: &Rules, position: &mut Position, next: &Move) {
+    match *next {
+        Move::Stock => {
+            turn_stock(rules, position);
+        }
+        Move::Cards {
+            source,
+            target,
+            quantity,
+        } => {
+            move_card(&mut position.piles, source, target, quantity);
+            fill_from_reserve(rules, &mut position.piles);
+        }
     }
+}
+
+/// Does what RET does under the rules. Returns false when nothing could be
+/// done.
+pub fn turn_stock(rules: &Rules, position: &mut Position) -> bool {
+    if !can_turn_stock(rules, position) {
+        return false;
+    }
+    match rules.stock {
+        Stock::Waste | Stock::Turn => {
+            let recycling = position.piles[0].is_empty();
+            if recycling {
+                position.passes += 1;
+            }
+            get_hand(&mut position.piles, rules.draw, rules.recycle)
+        }
+        Stock::Tableau => deal_tableau(rules, &mut position.piles),
+        Stock::Redeal => redeal(rules, position),
+        Stock::None => false,
+    }
+}
+
+/// Turns `draw` cards from the hand onto the waste. When the hand is empty
+/// the waste is turned over into a new hand, if `recycle` allows it.
+/// Returns false when nothing could be done.
+pub fn get_hand(piles: &mut [Vec<Card>], draw: usize, recycle: bool) -> bool {
+    let hand = match piles.get_mut(0) {
+        Some(p) => p,
+        None => exit(1),
+    };
 
     if hand.is_empty() {
-        match piles.get_mut(1) {
-            Some(p) => waste = p,
+        if !recycle {
+            return false;
+        }
+        let waste = match piles.get_mut(1) {
+            Some(p) => p,
             None => exit(1),
+        };
+        if waste.is_empty() {
+            return false;
         }
         waste.reverse();
-        hide_pile(&mut waste);
+        hide_pile(waste);
         piles.swap(0, 1);
-        return;
+        return true;
     }
 
-    let mut card: Card;
-    match hand.pop() {
-        Some(c) => card = c,
-        None => exit(1),
+    let mut cards: Vec<Card> = Vec::with_capacity(draw);
+    for _ in 0..draw {
+        match hand.pop() {
+            Some(mut c) => {
+                c.show();
+                cards.push(c);
+            }
+            None => break,
+        }
     }
 
-    card.show();
-
     match piles.get_mut(1) {
-        Some(p) => p.push(card),
+        Some(p) => p.append(&mut cards),
         None => exit(1),
     }
+    true
 }
 
-fn hide_pile(pile: &mut Vec<Card>) {
-    if !pile.is_empty() {
-        for i in 0..pile.len() {
-            match pile.get_mut(i) {
-                Some(c) => c.hide(),
-                None => exit(1),
-            }
+/// Deals the hand one card on each tableau pile, from the left, until it
+/// runs out. Returns false when the hand was already empty.
+pub fn deal_tableau(rules: &Rules, piles: &mut [Vec<Card>]) -> bool {
+    let mut dealt = false;
+    for i in rules.tableau_range() {
+        let mut card = match piles.get_mut(0) {
+            Some(p) => match p.pop() {
+                Some(c) => c,
+                None => break,
+            },
+            None => exit(1),
+        };
+        card.show();
+        match piles.get_mut(i) {
+            Some(p) => p.push(card),
+            None => exit(1),
         }
+        dealt = true;
     }
+    dealt
 }
 
-pub fn move_card(
-    piles: &mut Vec<Vec<Card>>,
-    source: usize,
-    target: usize,
-    quantity: usize,
-) -> bool {
-    if target > 8 && quantity > 1 {
+/// Gathers the tableau piles, shuffles them and deals them again face up,
+/// as many cards to each pile as the first pile got in the deal, until they
+/// run out. Returns false when no redeals are left.
+pub fn redeal(rules: &Rules, position: &mut Position) -> bool {
+    if position.passes >= rules.redeals {
         return false;
     }
+    position.passes += 1;
+    let mut cards: Vec<Card> = Vec::with_capacity(52 * rules.decks);
+    for i in rules.tableau_range() {
+        match position.piles.get_mut(i) {
+            Some(p) => cards.append(p),
+            None => exit(1),
+        }
+    }
+    deck::reshuffle_deck(&mut cards, position.game, position.passes);
+    let fan = match rules.tableaus.first() {
+        Some((facedown, faceup)) => facedown + faceup,
+        None => exit(1),
+    };
+    for i in rules.tableau_range() {
+        for _ in 0..fan {
+            match cards.pop() {
+                Some(mut c) => {
+                    c.show();
+                    position.piles[i].push(c);
+                }
+                None => return true,
+            }
+        }
+    }
+    true
+}
+
+fn hide_pile(pile: &mut [Card]) {
+    for c in pile.iter_mut() {
+        c.hide();
+    }
+}
 
-    let source_pile: &mut Vec<Card>;
+pub fn move_card(piles: &mut [Vec<Card>], source: usize, target: usize, quantity: usize) -> bool {
     let mut cards: Vec<Card> = Vec::new();
 
-    match piles.get_mut(source) {
-        Some(p) => source_pile = p,
+    let source_pile = match piles.get_mut(source) {
+        Some(p) => p,
         None => return false,
-    }
+    };
 
     for _ in 0..quantity {
         match source_pile.pop() {
@@ -71,12 +280,8 @@ pub fn move_card(
         }
     }
 
-    let source_pile_size = source_pile.len();
-    if source_pile_size > 0 {
-        match source_pile.get_mut(source_pile_size - 1) {
-            Some(c) => c.show(),
-            None => exit(1),
-        }
+    if let Some(c) = source_pile.last_mut() {
+        c.show();
     }
 
     match piles.get_mut(target) {
@@ -94,11 +299,7 @@ pub fn move_card(
     true
 }
 
-pub fn get_user_card(
-    piles: &Vec<Vec<Card>>,
-    piles_index: usize,
-    pile_index: usize,
-) -> Option<Card> {
+pub fn get_user_card(piles: &[Vec<Card>], piles_index: usize, pile_index: usize) -> Option<Card> {
     let pile = piles.get(piles_index);
     match pile {
         Some(p) => {
@@ -110,27 +311,67 @@ pub fn get_user_card(
             match card {
                 Some(c) => {
                     if c.is_faceup() {
-                        return Some(c.clone());
+                        return Some(*c);
                     }
-                    return None;
+                    None
                 }
-                None => return None,
+                None => None,
             }
         }
         None => exit(1),
     }
 }
 
-pub fn valid_moves(piles: &Vec<Vec<Card>>, user_card: &Card, piles_index: usize) -> usize {
-    if piles_index < 9 {
-        for i in 9..13 {
-            if i == piles_index {
-                continue;
+/// Whether the top `quantity` cards of a pile can be picked up together.
+pub fn can_move(rules: &Rules, piles: &[Vec<Card>], piles_index: usize, quantity: usize) -> bool {
+    if quantity == 1 {
+        return true;
+    }
+    if !rules.tableau_range().contains(&piles_index) {
+        return false;
+    }
+    match rules.movable {
+        Movable::Single => false,
+        Movable::FaceUp => true,
+        Movable::Sequence => {
+            let pile = match piles.get(piles_index) {
+                Some(p) => p,
+                None => exit(1),
+            };
+            if pile.len() < quantity {
+                return false;
+            }
+            if rules.cells > 0 && quantity > free_cells(rules, piles) + 1 {
+                return false;
             }
+            let cards = &pile[pile.len() - quantity..];
+            cards.windows(2).all(|w| builds_on(rules, &w[1], &w[0]))
+        }
+    }
+}
+
+pub fn valid_moves(
+    rules: &Rules,
+    piles: &[Vec<Card>],
+    user_card: &Card,
+    piles_index: usize,
+    quantity: usize,
+) -> usize {
+    for i in rules.foundation_range() {
+        if accepts(rules, piles, user_card, piles_index, i, quantity) {
+            return i;
+        }
+    }
+
+    // Building on a card is preferred over taking up an empty pile.
+    for empty in [false, true].iter() {
+        for i in rules.tableau_range() {
             let pile = piles.get(i);
             match pile {
                 Some(p) => {
-                    if valid_move_foundations(user_card, p) {
+                    if p.is_empty() == *empty
+                        && accepts(rules, piles, user_card, piles_index, i, quantity)
+                    {
                         return i;
                     }
                 }
@@ -139,74 +380,233 @@ pub fn valid_moves(piles: &Vec<Vec<Card>>, user_card: &Card, piles_index: usize)
         }
     }
 
-    for i in 2..9 {
-        if i == piles_index {
-            continue;
+    0
+}
+
+/// Whether the top `quantity` cards of the source pile, `user_card` being
+/// the bottom one, can be put on the target pile.
+pub fn accepts(
+    rules: &Rules,
+    piles: &[Vec<Card>],
+    user_card: &Card,
+    source: usize,
+    target: usize,
+    quantity: usize,
+) -> bool {
+    if source == target {
+        return false;
+    }
+    let from_foundation = rules.foundation_range().contains(&source);
+    if from_foundation && !rules.from_foundations {
+        return false;
+    }
+    let pile = match piles.get(target) {
+        Some(p) => p,
+        None => exit(1),
+    };
+    if rules.foundation_range().contains(&target) {
+        if quantity != 1 || from_foundation {
+            return false;
         }
-        let pile = piles.get(i);
-        match pile {
+        return match rules.foundation {
+            Foundation::InSuit => valid_move_foundations(rules, user_card, pile),
+            Foundation::Discard => can_discard(rules, piles, user_card),
+            Foundation::Intervals => {
+                valid_move_intervals(user_card, pile, target - rules.foundation_range().start + 1)
+            }
+        };
+    }
+    if rules.tableau_range().contains(&target) {
+        if rules.build == Build::FromWaste {
+            return source == 1 && quantity == 1;
+        }
+        return valid_move_tableau(rules, user_card, pile);
+    }
+    if rules.cell_range().contains(&target) {
+        return quantity == 1
+            && pile.is_empty()
+            && !from_foundation
+            && !rules.cell_range().contains(&source);
+    }
+    false
+}
+
+/// Whether another tableau pile shows a higher card of the same suit.
+fn can_discard(rules: &Rules, piles: &[Vec<Card>], card: &Card) -> bool {
+    rules.tableau_range().any(|i| match piles.get(i) {
+        Some(p) => match p.last() {
+            Some(c) => {
+                c.get_card_suit() == card.get_card_suit() && rules.rank(c) > rules.rank(card)
+            }
+            None => false,
+        },
+        None => exit(1),
+    })
+}
+
+fn free_cells(rules: &Rules, piles: &[Vec<Card>]) -> usize {
+    rules
+        .cell_range()
+        .filter(|i| match piles.get(*i) {
+            Some(p) => p.is_empty(),
+            None => exit(1),
+        })
+        .count()
+}
+
+/// First empty free cell the top card of a pile can be put in, or 0 when
+/// there is none.
+pub fn free_cell(rules: &Rules, piles: &[Vec<Card>], piles_index: usize) -> usize {
+    if rules.cell_range().contains(&piles_index) || rules.foundation_range().contains(&piles_index)
+    {
+        return 0;
+    }
+    for i in rules.cell_range() {
+        match piles.get(i) {
             Some(p) => {
-                if valid_move_tableau(user_card, p) {
+                if p.is_empty() {
                     return i;
                 }
             }
             None => exit(1),
         }
     }
-
     0
 }
 
-fn valid_move_tableau(card: &Card, pile: &Vec<Card>) -> bool {
-    let size = pile.len();
-    if size == 0 {
-        match card.get_card_value() {
-            CardValue::K => return true,
-            _ => return false,
-        }
+pub fn builds_on(rules: &Rules, card: &Card, last_card: &Card) -> bool {
+    let wrapped = rules.wrap && last_card.get_value() == 1 && card.get_value() == 13;
+    if last_card.get_value() != card.get_value() + 1 && !wrapped {
+        return false;
     }
+    match rules.build {
+        Build::AlternateColors => last_card.get_card_color() != card.get_card_color(),
+        Build::SameSuit => last_card.get_card_suit() == card.get_card_suit(),
+        Build::AnySuit => true,
+        Build::FromWaste | Build::None => false,
+    }
+}
 
-    let last_card: &Card;
-    match pile.get(size - 1) {
-        Some(x) => last_card = x,
-        None => exit(1),
+fn valid_move_tableau(rules: &Rules, card: &Card, pile: &[Card]) -> bool {
+    match pile.last() {
+        Some(last_card) => builds_on(rules, card, last_card),
+        None => match rules.empty {
+            Empty::Kings => matches!(card.get_card_value(), CardValue::K),
+            Empty::Any => true,
+            Empty::None => false,
+        },
     }
+}
 
-    if last_card.get_value() == card.get_value() + 1 {
-        match (last_card.get_card_color(), card.get_card_color()) {
-            (CardColor::Black, CardColor::Black) => return false,
-            (CardColor::Red, CardColor::Red) => return false,
-            _ => return true,
+/// Foundations are built up in suit from the base rank, turning the corner
+/// from king to ace, until they hold 13 cards.
+fn valid_move_foundations(rules: &Rules, card: &Card, pile: &[Card]) -> bool {
+    if pile.len() >= 13 {
+        return false;
+    }
+    match pile.last() {
+        Some(last_card) => {
+            last_card.get_value() % 13 + 1 == card.get_value()
+                && last_card.get_card_suit() == card.get_card_suit()
         }
+        None => card.get_value() == rules.base,
     }
-
-    false
 }
 
-fn valid_move_foundations(card: &Card, pile: &Vec<Card>) -> bool {
-    let size = pile.len();
-    if size == 0 {
-        match card.get_card_value() {
-            CardValue::A => return true,
-            _ => return false,
+/// Foundations counted from 1 are built up by that many ranks at a time,
+/// in any suit, turning the corner from king to ace.
+fn valid_move_intervals(card: &Card, pile: &[Card], step: usize) -> bool {
+    if pile.len() >= 13 {
+        return false;
+    }
+    match pile.last() {
+        Some(last_card) => {
+            (last_card.get_value() as usize + step - 1) % 13 + 1 == card.get_value() as usize
         }
+        None => card.get_value() as usize == step,
     }
+}
 
-    let last_card: &Card;
-    match pile.get(size - 1) {
-        Some(x) => last_card = x,
-        None => exit(1),
+/// Moves the top card of the reserve into every empty tableau pile, while
+/// the reserve lasts.
+pub fn fill_from_reserve(rules: &Rules, piles: &mut [Vec<Card>]) {
+    let reserve = match rules.reserve_index() {
+        Some(i) => i,
+        None => return,
+    };
+    for i in rules.tableau_range() {
+        let empty = match piles.get(i) {
+            Some(p) => p.is_empty(),
+            None => exit(1),
+        };
+        let reserve_empty = match piles.get(reserve) {
+            Some(p) => p.is_empty(),
+            None => exit(1),
+        };
+        if empty && !reserve_empty {
+            move_card(piles, reserve, i, 1);
+        }
     }
+}
+
+/// Whether a pile holds a whole suit built down from king to ace.
+pub fn is_complete_run(rules: &Rules, pile: &[Card]) -> bool {
+    pile.len() == 13
+        && pile.iter().all(|c| c.is_faceup())
+        && pile.windows(2).all(|w| builds_on(rules, &w[1], &w[0]))
+}
 
-    if last_card.get_value() == card.get_value() - 1 {
-        match (last_card.get_card_suit(), card.get_card_suit()) {
-            (CardType::Corazones, CardType::Corazones) => return true,
-            (CardType::Diamantes, CardType::Diamantes) => return true,
-            (CardType::Espadas, CardType::Espadas) => return true,
-            (CardType::Treboles, CardType::Treboles) => return true,
-            _ => return false,
+/// Whether the goal of the game has been reached.
+pub fn game_over(rules: &Rules, piles: &[Vec<Card>]) -> bool {
+    if rules.goal == Goal::Runs {
+        for i in rules.tableau_range() {
+            match piles.get(i) {
+                Some(p) => {
+                    if !p.is_empty() && !is_complete_run(rules, p) {
+                        return false;
+                    }
+                }
+                None => exit(1),
+            }
+        }
+        return true;
+    }
+    if rules.goal == Goal::Aces {
+        return rules.tableau_range().all(|i| match piles.get(i) {
+            Some(p) => p.iter().all(|c| c.get_value() == 1) && piles[0].is_empty(),
+            None => exit(1),
+        });
+    }
+    for i in rules.foundation_range() {
+        match piles.get(i) {
+            Some(p) => {
+                if p.len() < 13 {
+                    return false;
+                }
+            }
+            None => exit(1),
         }
     }
+    true
+}
 
-    false
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deck;
+
+    #[test]
+    fn cards_come_off_the_foundations_only_where_the_rules_say() {
+        let mut two = deck::new_deck(1)[1];
+        two.show();
+        let mut three = deck::new_deck(1)[15];
+        three.show();
+        for (rules, allowed) in [(Rules::klondike(), true), (Rules::canfield(), false)].iter() {
+            let mut piles: Vec<Vec<Card>> = vec![Vec::new(); rules.piles()];
+            let foundation = rules.foundation_range().start;
+            piles[foundation].push(two);
+            piles[2].push(three);
+            assert_eq!(accepts(rules, &piles, &two, foundation, 2, 1), *allowed);
+        }
+    }
 }
@@ -0,0 +1,215 @@
+use crate::moves::{self, Position};
+use crate::rules::{Difficulty, Rules};
+use crate::solver::{self, Limits, Outcome};
+use log::info;
+use rand::Rng;
+use std::time::{Duration, Instant};
+
+const LIMITS: Limits = Limits {
+    nodes: 10_000,
+    time: Duration::from_secs(1),
+};
+/// Seconds spent looking for a deal of the difficulty asked for.
+const PICK_TIME: u64 = 15;
+const EASY: u32 = 36;
+const MEDIUM: u32 = 46;
+
+/// How hard a deal is, from 0 to 100, and what the score is made of.
+#[derive(Debug, Clone)]
+pub struct Rating {
+    /// None unless the solver won the deal.
+    pub score: Option<u32>,
+    /// None when the solver gave up before deciding.
+    pub winnable: Option<bool>,
+    /// Positions the solver searched.
+    pub nodes: u64,
+    /// Length of the solution found.
+    pub moves: Option<usize>,
+    /// Aces and kings dealt with cards on top of them.
+    pub buried: usize,
+    /// Legal moves on average along the solution, or in the deal when
+    /// there is none.
+    pub branching: f64,
+}
+
+/// Rates a deal by solving it. Only deals the solver wins get a score.
+pub fn rate(rules: &Rules, game: u64) -> Rating {
+    let (rules, start) = Position::deal(rules, game);
+    let report = solver::solve(&rules, &start, LIMITS);
+    let buried = buried(&rules, &start);
+    let cards = 52 * rules.decks;
+
+    let (winnable, moves, branching) = match &report.outcome {
+        Outcome::Won(path) => {
+            let mut position = start.clone();
+            let mut legal = 0;
+            for next in path {
+                legal += moves::legal_moves(&rules, &position).len();
+                moves::play(&rules, &mut position, next);
+            }
+            let branching = legal as f64 / path.len().max(1) as f64;
+            (Some(true), Some(path.len()), branching)
+        }
+        Outcome::Unwinnable => (Some(false), None, legal(&rules, &start)),
+        Outcome::Unknown => (None, None, legal(&rules, &start)),
+    };
+
+    let score = moves.map(|length| {
+        let effort = (report.nodes as f64 + 1.0).ln() / (LIMITS.nodes as f64).ln();
+        let length = length as f64 / (cards * 10) as f64;
+        let buried = buried as f64 / (8 * rules.decks) as f64;
+        let branching = 1.0 - branching / 10.0;
+        let score = 45.0 * effort.min(1.0)
+            + 9.0 * length.min(1.0)
+            + 18.0 * buried.min(1.0)
+            + 28.0 * branching.clamp(0.0, 1.0);
+        (score.round() as u32).min(100)
+    });
+    Rating {
+        score,
+        winnable,
+        nodes: report.nodes,
+        moves,
+        buried,
+        branching,
+    }
+}
+
+impl Rating {
+    pub fn difficulty(&self) -> Option<Difficulty> {
+        self.score.map(difficulty)
+    }
+}
+
+fn difficulty(score: u32) -> Difficulty {
+    if score < EASY {
+        Difficulty::Easy
+    } else if score < MEDIUM {
+        Difficulty::Medium
+    } else {
+        Difficulty::Hard
+    }
+}
+
+fn legal(rules: &Rules, position: &Position) -> f64 {
+    moves::legal_moves(rules, position).len() as f64
+}
+
+fn buried(rules: &Rules, position: &Position) -> usize {
+    rules
+        .tableau_range()
+        .map(|i| {
+            let pile = &position.piles[i];
+            pile.iter()
+                .take(pile.len().saturating_sub(1))
+                .filter(|c| c.get_value() == 1 || c.get_value() == 13)
+                .count()
+        })
+        .sum()
+}
+
+/// Draws deals until one the solver wins is rated as hard as `wanted`,
+/// returning it with its rating. When time runs out the closest one is
+/// dealt.
+pub fn pick_game(rules: &Rules, wanted: Difficulty) -> (u64, Rating) {
+    let target = match wanted {
+        Difficulty::Easy => 0,
+        Difficulty::Medium => (EASY + MEDIUM) / 2,
+        Difficulty::Hard => 100,
+    };
+    let budget = rules
+        .winnable
+        .unwrap_or_else(|| Duration::from_secs(PICK_TIME));
+    let mut rng = rand::thread_rng();
+    let start = Instant::now();
+    let mut closest: Option<(u64, Rating, u32)> = None;
+    println!("Looking for a deal rated {}...", wanted.name());
+    while start.elapsed() < budget {
+        let game = rng.gen_range(1, 1000000);
+        let rating = rate(rules, game);
+        let score = match rating.score {
+            Some(s) => s,
+            None => continue,
+        };
+        if difficulty(score) == wanted {
+            info!("Game {} rated {:?}", game, rating);
+            return (game, rating);
+        }
+        let distance = (score as i64 - target as i64).unsigned_abs() as u32;
+        if closest.as_ref().map_or(true, |(_, _, d)| distance < *d) {
+            closest = Some((game, rating, distance));
+        }
+    }
+    match closest {
+        Some((game, rating, _)) => {
+            println!(
+                "No deal rated {} found, dealing game {}",
+                wanted.name(),
+                game
+            );
+            (game, rating)
+        }
+        None => {
+            let game = rng.gen_range(1, 1000000);
+            println!("No deal could be rated, dealing game {}", game);
+            (game, rate(rules, game))
+        }
+    }
+}
+
+/// Prints what the rating of a deal is made of.
+pub fn start(rules: &Rules, mut game: u64) {
+    if game == 0 {
+        game = rand::thread_rng().gen_range(1, 1000000);
+    }
+    let rating = rate(rules, game);
+    let winnable = match rating.winnable {
+        Some(true) => "yes",
+        Some(false) => "no",
+        None => "unknown",
+    };
+    match (rating.difficulty(), rating.score) {
+        (Some(d), Some(score)) => println!("Game {}: {} ({}/100)", game, d.name(), score),
+        _ => println!("Game {}: unrated", game),
+    }
+    println!("Winnable: {}", winnable);
+    println!("Positions searched: {}", rating.nodes);
+    match rating.moves {
+        Some(m) => println!("Solution length: {}", m),
+        None => println!("Solution length: -"),
+    }
+    println!("Buried aces and kings: {}", rating.buried);
+    println!("Average moves to choose from: {:.1}", rating.branching);
+    info!("Rated game {}: {:?}", game, rating);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_fall_into_bands() {
+        assert_eq!(difficulty(0), Difficulty::Easy);
+        assert_eq!(difficulty(EASY - 1), Difficulty::Easy);
+        assert_eq!(difficulty(EASY), Difficulty::Medium);
+        assert_eq!(difficulty(MEDIUM - 1), Difficulty::Medium);
+        assert_eq!(difficulty(MEDIUM), Difficulty::Hard);
+        assert_eq!(difficulty(100), Difficulty::Hard);
+    }
+
+    #[test]
+    fn won_deal_is_scored() {
+        let rating = rate(&Rules::klondike(), 77);
+        assert_eq!(rating.winnable, Some(true));
+        assert!(rating.score.map_or(false, |s| s <= 100));
+        assert!(rating.difficulty().is_some());
+    }
+
+    #[test]
+    fn unwinnable_deal_is_unrated() {
+        let rating = rate(&Rules::canfield(), 5);
+        assert_eq!(rating.winnable, Some(false));
+        assert_eq!(rating.score, None);
+        assert_eq!(rating.difficulty(), None);
+    }
+}
@@ -0,0 +1,591 @@
+use crate::card::Card;
+use std::ops::Range;
+use std::process::exit;
+use std::time::Duration;
+
+const TABLEAU_KEYS: &str = "1234567890asdfgjkl";
+const FOUNDATION_KEYS: &str = "qwertyop";
+const CELL_KEYS: &str = "zxcvbm,.";
+const RESERVE_KEY: char = 'z';
+/// Every game `Rules::from_name` knows.
+#[cfg(test)]
+pub const GAMES: [&str; 11] = [
+    "klondike",
+    "doubleklondike",
+    "fortythieves",
+    "bakersdozen",
+    "scorpion",
+    "canfield",
+    "eightoff",
+    "russian",
+    "labellelucie",
+    "acesup",
+    "calculation",
+];
+
+/// How cards are built down on the tableau piles.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Build {
+    AlternateColors,
+    SameSuit,
+    AnySuit,
+    /// Any card from the waste, and nothing else, goes on any pile.
+    FromWaste,
+    /// Cards only move to empty piles.
+    None,
+}
+
+/// What the foundations take.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Foundation {
+    /// Built up in suit from the base rank.
+    InSuit,
+    /// Any top card lower than another top card of its suit is discarded.
+    Discard,
+    /// Dealt the first four ranks, each is built up in any suit by its own
+    /// rank at a time.
+    Intervals,
+}
+
+/// Which cards can be moved into an empty tableau pile.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Empty {
+    Kings,
+    Any,
+    None,
+}
+
+/// How many cards can be moved from a tableau pile at once.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Movable {
+    Single,
+    Sequence,
+    /// Any face up card together with everything on top of it.
+    FaceUp,
+}
+
+/// Where the cards left after the deal go.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Stock {
+    Waste,
+    /// Dealt one card on each tableau pile.
+    Tableau,
+    /// Turned onto the waste one card at a time, once the last one has been
+    /// played.
+    Turn,
+    /// Nothing is left after the deal. The tableau is gathered, shuffled
+    /// and dealt again instead, `redeals` times.
+    Redeal,
+    None,
+}
+
+/// What has to be done to win the game.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Goal {
+    /// Every foundation holds 13 cards.
+    Foundations,
+    /// Every tableau pile is either empty or a king to ace run in suit.
+    Runs,
+    /// Only the aces are left on the tableau.
+    Aces,
+}
+
+/// How hard a deal is to win, as rated from searching it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "easy" => Some(Self::Easy),
+            "medium" => Some(Self::Medium),
+            "hard" => Some(Self::Hard),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Easy => "easy",
+            Self::Medium => "medium",
+            Self::Hard => "hard",
+        }
+    }
+}
+
+/// Layout and rules of a game played on the pile table. Piles are indexed
+/// with the hand at 0, the waste at 1, then the tableau piles, the
+/// foundations and the reserve.
+#[derive(Debug, Clone)]
+pub struct Rules {
+    /// Name the game is picked with.
+    pub name: &'static str,
+    pub decks: usize,
+    /// Face down and face up cards dealt to each tableau pile.
+    pub tableaus: Vec<(usize, usize)>,
+    /// Kings are moved to the bottom of their tableau pile when dealt.
+    pub kings_to_bottom: bool,
+    /// Cards dealt to the reserve pile, its top card face up. Empty tableau
+    /// piles are filled from the reserve while it lasts.
+    pub reserve: usize,
+    pub foundations: usize,
+    pub foundation: Foundation,
+    /// Cards can be moved off the foundations back onto the tableau.
+    pub from_foundations: bool,
+    /// Rank the foundations are built up from.
+    pub base: u8,
+    /// A card is dealt to the first foundation and sets the `base` rank.
+    pub base_card: bool,
+    /// Free cells, each holding a single card. The cards left after the deal
+    /// are put in them. Sequences can only be moved one card more than there
+    /// are free cells empty.
+    pub cells: usize,
+    pub build: Build,
+    /// Tableau piles are built down from aces to kings.
+    pub wrap: bool,
+    /// Aces rank above kings.
+    pub aces_high: bool,
+    pub empty: Empty,
+    pub movable: Movable,
+    pub stock: Stock,
+    pub draw: usize,
+    pub recycle: bool,
+    pub redeals: usize,
+    pub goal: Goal,
+    /// New games are only dealt once the solver has won them, searching
+    /// for at most this long.
+    pub winnable: Option<Duration>,
+    /// New games are only dealt once they are rated this hard.
+    pub difficulty: Option<Difficulty>,
+}
+
+impl Rules {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "klondike" => Some(Self::klondike()),
+            "doubleklondike" => Some(Self::double_klondike()),
+            "fortythieves" => Some(Self::forty_thieves()),
+            "bakersdozen" => Some(Self::bakers_dozen()),
+            "scorpion" => Some(Self::scorpion()),
+            "canfield" => Some(Self::canfield()),
+            "eightoff" => Some(Self::eight_off()),
+            "russian" => Some(Self::russian()),
+            "labellelucie" => Some(Self::la_belle_lucie()),
+            "acesup" => Some(Self::aces_up()),
+            "calculation" => Some(Self::calculation()),
+            _ => None,
+        }
+    }
+
+    pub fn klondike() -> Self {
+        Self {
+            name: "klondike",
+            decks: 1,
+            tableaus: (0..7).map(|i| (i, 1)).collect(),
+            kings_to_bottom: false,
+            reserve: 0,
+            foundations: 4,
+            foundation: Foundation::InSuit,
+            from_foundations: true,
+            base: 1,
+            base_card: false,
+            cells: 0,
+            build: Build::AlternateColors,
+            wrap: false,
+            aces_high: false,
+            empty: Empty::Kings,
+            movable: Movable::Sequence,
+            stock: Stock::Waste,
+            draw: 1,
+            recycle: true,
+            redeals: 0,
+            goal: Goal::Foundations,
+            winnable: None,
+            difficulty: None,
+        }
+    }
+
+    pub fn double_klondike() -> Self {
+        Self {
+            name: "doubleklondike",
+            decks: 2,
+            tableaus: (0..9).map(|i| (i, 1)).collect(),
+            kings_to_bottom: false,
+            reserve: 0,
+            foundations: 8,
+            foundation: Foundation::InSuit,
+            from_foundations: true,
+            base: 1,
+            base_card: false,
+            cells: 0,
+            build: Build::AlternateColors,
+            wrap: false,
+            aces_high: false,
+            empty: Empty::Kings,
+            movable: Movable::Sequence,
+            stock: Stock::Waste,
+            draw: 1,
+            recycle: true,
+            redeals: 0,
+            goal: Goal::Foundations,
+            winnable: None,
+            difficulty: None,
+        }
+    }
+
+    pub fn forty_thieves() -> Self {
+        Self {
+            name: "fortythieves",
+            decks: 2,
+            tableaus: vec![(0, 4); 10],
+            kings_to_bottom: false,
+            reserve: 0,
+            foundations: 8,
+            foundation: Foundation::InSuit,
+            from_foundations: false,
+            base: 1,
+            base_card: false,
+            cells: 0,
+            build: Build::SameSuit,
+            wrap: false,
+            aces_high: false,
+            empty: Empty::Any,
+            movable: Movable::Single,
+            stock: Stock::Waste,
+            draw: 1,
+            recycle: false,
+            redeals: 0,
+            goal: Goal::Foundations,
+            winnable: None,
+            difficulty: None,
+        }
+    }
+
+    pub fn bakers_dozen() -> Self {
+        Self {
+            name: "bakersdozen",
+            decks: 1,
+            tableaus: vec![(0, 4); 13],
+            kings_to_bottom: true,
+            reserve: 0,
+            foundations: 4,
+            foundation: Foundation::InSuit,
+            from_foundations: false,
+            base: 1,
+            base_card: false,
+            cells: 0,
+            build: Build::AnySuit,
+            wrap: false,
+            aces_high: false,
+            empty: Empty::None,
+            movable: Movable::Single,
+            stock: Stock::None,
+            draw: 0,
+            recycle: false,
+            redeals: 0,
+            goal: Goal::Foundations,
+            winnable: None,
+            difficulty: None,
+        }
+    }
+
+    pub fn scorpion() -> Self {
+        let mut tableaus = vec![(3, 4); 4];
+        tableaus.append(&mut vec![(0, 7); 3]);
+        Self {
+            name: "scorpion",
+            decks: 1,
+            tableaus,
+            kings_to_bottom: false,
+            reserve: 0,
+            foundations: 0,
+            foundation: Foundation::InSuit,
+            from_foundations: false,
+            base: 1,
+            base_card: false,
+            cells: 0,
+            build: Build::SameSuit,
+            wrap: false,
+            aces_high: false,
+            empty: Empty::Kings,
+            movable: Movable::FaceUp,
+            stock: Stock::Tableau,
+            draw: 0,
+            recycle: false,
+            redeals: 0,
+            goal: Goal::Runs,
+            winnable: None,
+            difficulty: None,
+        }
+    }
+
+    pub fn canfield() -> Self {
+        Self {
+            name: "canfield",
+            decks: 1,
+            tableaus: vec![(0, 1); 4],
+            kings_to_bottom: false,
+            reserve: 13,
+            foundations: 4,
+            foundation: Foundation::InSuit,
+            from_foundations: false,
+            base: 1,
+            base_card: true,
+            cells: 0,
+            build: Build::AlternateColors,
+            wrap: true,
+            aces_high: false,
+            empty: Empty::Any,
+            movable: Movable::Sequence,
+            stock: Stock::Waste,
+            draw: 3,
+            recycle: true,
+            redeals: 0,
+            goal: Goal::Foundations,
+            winnable: None,
+            difficulty: None,
+        }
+    }
+
+    pub fn eight_off() -> Self {
+        Self {
+            name: "eightoff",
+            decks: 1,
+            tableaus: vec![(0, 6); 8],
+            kings_to_bottom: false,
+            reserve: 0,
+            foundations: 4,
+            foundation: Foundation::InSuit,
+            from_foundations: false,
+            base: 1,
+            base_card: false,
+            cells: 8,
+            build: Build::SameSuit,
+            wrap: false,
+            aces_high: false,
+            empty: Empty::Kings,
+            movable: Movable::Sequence,
+            stock: Stock::None,
+            draw: 0,
+            recycle: false,
+            redeals: 0,
+            goal: Goal::Foundations,
+            winnable: None,
+            difficulty: None,
+        }
+    }
+
+    /// Yukon layout built down in suit.
+    pub fn russian() -> Self {
+        let mut tableaus = vec![(0, 1)];
+        tableaus.extend((1..7).map(|i| (i, 5)));
+        Self {
+            name: "russian",
+            decks: 1,
+            tableaus,
+            kings_to_bottom: false,
+            reserve: 0,
+            foundations: 4,
+            foundation: Foundation::InSuit,
+            from_foundations: false,
+            base: 1,
+            base_card: false,
+            cells: 0,
+            build: Build::SameSuit,
+            wrap: false,
+            aces_high: false,
+            empty: Empty::Kings,
+            movable: Movable::FaceUp,
+            stock: Stock::None,
+            draw: 0,
+            recycle: false,
+            redeals: 0,
+            goal: Goal::Foundations,
+            winnable: None,
+            difficulty: None,
+        }
+    }
+
+    pub fn la_belle_lucie() -> Self {
+        let mut tableaus = vec![(0, 3); 17];
+        tableaus.push((0, 1));
+        Self {
+            name: "labellelucie",
+            decks: 1,
+            tableaus,
+            kings_to_bottom: false,
+            reserve: 0,
+            foundations: 4,
+            foundation: Foundation::InSuit,
+            from_foundations: false,
+            base: 1,
+            base_card: false,
+            cells: 0,
+            build: Build::SameSuit,
+            wrap: false,
+            aces_high: false,
+            empty: Empty::None,
+            movable: Movable::Single,
+            stock: Stock::Redeal,
+            draw: 0,
+            recycle: false,
+            redeals: 2,
+            goal: Goal::Foundations,
+            winnable: None,
+            difficulty: None,
+        }
+    }
+
+    pub fn aces_up() -> Self {
+        Self {
+            name: "acesup",
+            decks: 1,
+            tableaus: vec![(0, 1); 4],
+            kings_to_bottom: false,
+            reserve: 0,
+            foundations: 1,
+            foundation: Foundation::Discard,
+            from_foundations: false,
+            base: 1,
+            base_card: false,
+            cells: 0,
+            build: Build::None,
+            wrap: false,
+            aces_high: true,
+            empty: Empty::Any,
+            movable: Movable::Single,
+            stock: Stock::Tableau,
+            draw: 0,
+            recycle: false,
+            redeals: 0,
+            goal: Goal::Aces,
+            winnable: None,
+            difficulty: None,
+        }
+    }
+
+    pub fn calculation() -> Self {
+        Self {
+            name: "calculation",
+            decks: 1,
+            tableaus: vec![(0, 0); 4],
+            kings_to_bottom: false,
+            reserve: 0,
+            foundations: 4,
+            foundation: Foundation::Intervals,
+            from_foundations: false,
+            base: 1,
+            base_card: false,
+            cells: 0,
+            build: Build::FromWaste,
+            wrap: false,
+            aces_high: false,
+            empty: Empty::Any,
+            movable: Movable::Single,
+            stock: Stock::Turn,
+            draw: 1,
+            recycle: false,
+            redeals: 0,
+            goal: Goal::Foundations,
+            winnable: None,
+            difficulty: None,
+        }
+    }
+
+    /// Rank of a card under these rules, aces counting as 14 when high.
+    pub fn rank(&self, card: &Card) -> u8 {
+        if self.aces_high && card.get_value() == 1 {
+            return 14;
+        }
+        card.get_value()
+    }
+
+    pub fn tableau_range(&self) -> Range<usize> {
+        2..2 + self.tableaus.len()
+    }
+
+    pub fn foundation_range(&self) -> Range<usize> {
+        let start = self.tableau_range().end;
+        start..start + self.foundations
+    }
+
+    pub fn cell_range(&self) -> Range<usize> {
+        let start = self.foundation_range().end;
+        start..start + self.cells
+    }
+
+    pub fn reserve_index(&self) -> Option<usize> {
+        if self.reserve == 0 {
+            return None;
+        }
+        Some(self.cell_range().end)
+    }
+
+    /// Whether which tableau pile a card is on matters: the reserve fills
+    /// them and the stock deals or redeals to them in order.
+    pub fn tableau_ordered(&self) -> bool {
+        self.reserve > 0 || self.stock == Stock::Tableau || self.stock == Stock::Redeal
+    }
+
+    pub fn piles(&self) -> usize {
+        match self.reserve_index() {
+            Some(i) => i + 1,
+            None => self.cell_range().end,
+        }
+    }
+
+    pub fn key_to_index(&self, key: char) -> Option<usize> {
+        if key == 'h' {
+            return Some(1);
+        }
+        if key == RESERVE_KEY && self.reserve_index().is_some() {
+            return self.reserve_index();
+        }
+
+        if let Some(i) = TABLEAU_KEYS.find(key) {
+            if i < self.tableaus.len() {
+                return Some(self.tableau_range().start + i);
+            }
+        }
+        if let Some(i) = FOUNDATION_KEYS.find(key) {
+            if i < self.foundations {
+                return Some(self.foundation_range().start + i);
+            }
+        }
+        if let Some(i) = CELL_KEYS.find(key) {
+            if i < self.cells {
+                return Some(self.cell_range().start + i);
+            }
+        }
+        None
+    }
+
+    pub fn index_to_str(&self, index: usize) -> String {
+        match index {
+            0 => return String::from("Hand"),
+            1 => return String::from("Waste"),
+            _ => {}
+        }
+        let key = if self.tableau_range().contains(&index) {
+            TABLEAU_KEYS.chars().nth(index - self.tableau_range().start)
+        } else if self.foundation_range().contains(&index) {
+            FOUNDATION_KEYS
+                .chars()
+                .nth(index - self.foundation_range().start)
+        } else if self.cell_range().contains(&index) {
+            CELL_KEYS.chars().nth(index - self.cell_range().start)
+        } else if self.reserve_index() == Some(index) {
+            Some(RESERVE_KEY)
+        } else {
+            None
+        };
+        match key {
+            Some(k) => k.to_string(),
+            None => exit(1),
+        }
+    }
+}
@@ -0,0 +1,767 @@
+use crate::bot;
+use crate::card::Card;
+use crate::moves::{self, Move, Position};
+use crate::rules::{Build, Foundation, Rules, Stock};
+use crate::zobrist::{Hash, Table, Zobrist};
+use log::info;
+use rand::Rng;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::process::exit;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const NODES: u64 = 1_000_000;
+pub const TIME: u64 = 30;
+/// Seconds spent looking for a winnable deal when `--time` isn't given.
+pub const WINNABLE_TIME: u64 = 10;
+const WINNABLE_DIR: &str = "winnable";
+const DEAD_END_NODES: usize = 2_000;
+const HINT_LIMITS: Limits = Limits {
+    nodes: 20_000,
+    time: Duration::from_secs(1),
+};
+/// Limits of the search for a solution to replay from the current position.
+pub const REPLAY_LIMITS: Limits = Limits {
+    nodes: NODES,
+    time: Duration::from_secs(8),
+};
+/// Limits of the search from the deal, tried when none is found from the
+/// current position, so both together take at most ten seconds.
+pub const REPLAY_DEAL_LIMITS: Limits = Limits {
+    nodes: 200_000,
+    time: Duration::from_secs(2),
+};
+
+/// How far a search may go before giving up.
+#[derive(Debug, Copy, Clone)]
+pub struct Limits {
+    pub nodes: u64,
+    pub time: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    /// The moves that win the game.
+    Won(Vec<Move>),
+    /// Every position reachable from the deal was searched without a win.
+    Unwinnable,
+    /// A limit was reached first.
+    Unknown,
+}
+
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub outcome: Outcome,
+    /// Positions searched.
+    pub nodes: u64,
+    pub elapsed: Duration,
+}
+
+/// A position on the search path and the moves from it not tried yet.
+struct Frame {
+    position: Position,
+    hash: Hash,
+    /// Moves that led here from the previous frame.
+    arrival: Vec<Move>,
+    /// Each entry is played as a whole.
+    untried: Vec<Vec<Move>>,
+}
+
+pub fn start(rules: &Rules, mut game: u64, limits: Limits) {
+    if game == 0 {
+        let mut rng = rand::thread_rng();
+        game = rng.gen_range(1, 1000000);
+    }
+    let (rules, position) = Position::deal(rules, game);
+    let report = solve(&rules, &position, limits);
+    let seconds = report.elapsed.as_secs_f64();
+    match &report.outcome {
+        Outcome::Won(path) => {
+            println!(
+                "Game {}: won in {} moves, {} positions searched in {:.3}s",
+                game,
+                path.len(),
+                report.nodes,
+                seconds
+            );
+            for (i, step) in path.iter().enumerate() {
+                println!("{:>4}. {}", i + 1, step.describe(&rules));
+            }
+        }
+        Outcome::Unwinnable => println!(
+            "Game {}: unwinnable, all {} positions searched in {:.3}s",
+            game, report.nodes, seconds
+        ),
+        Outcome::Unknown => println!(
+            "Game {}: unknown, gave up after {} positions in {:.3}s",
+            game, report.nodes, seconds
+        ),
+    }
+    let result = match report.outcome {
+        Outcome::Won(path) => format!("won in {} moves", path.len()),
+        Outcome::Unwinnable => String::from("unwinnable"),
+        Outcome::Unknown => String::from("unknown"),
+    };
+    info!(
+        "Solved game {}: {} after {} positions in {:?}",
+        game, result, report.nodes, report.elapsed
+    );
+}
+
+/// Solves the deals from `first` on, one per core at a time, and writes a
+/// line for each to a CSV file.
+pub fn batch(rules: &Rules, first: u64, deals: u64, limits: Limits, file_name: &str) {
+    let start = Instant::now();
+    let next = Arc::new(AtomicU64::new(first));
+    let (sender, receiver) = mpsc::channel();
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    for _ in 0..threads {
+        let next = Arc::clone(&next);
+        let sender = sender.clone();
+        let rules = rules.clone();
+        thread::spawn(move || loop {
+            let game = next.fetch_add(1, Ordering::Relaxed);
+            if game >= first + deals {
+                break;
+            }
+            let (rules, position) = Position::deal(&rules, game);
+            if sender
+                .send((game, solve(&rules, &position, limits)))
+                .is_err()
+            {
+                break;
+            }
+        });
+    }
+    drop(sender);
+
+    let mut reports: Vec<(u64, Report)> = Vec::with_capacity(deals as usize);
+    for (game, report) in receiver {
+        reports.push((game, report));
+        if reports.len() % 100 == 0 {
+            println!("{} of {} deals solved", reports.len(), deals);
+        }
+    }
+    reports.sort_by_key(|(game, _)| *game);
+
+    let mut csv = String::from("seed,winnable,moves,nodes,seconds\n");
+    let (mut won, mut unwinnable) = (0, 0);
+    for (game, report) in reports.iter() {
+        let (winnable, length) = match &report.outcome {
+            Outcome::Won(path) => {
+                won += 1;
+                ("yes", path.len().to_string())
+            }
+            Outcome::Unwinnable => {
+                unwinnable += 1;
+                ("no", String::from(""))
+            }
+            Outcome::Unknown => ("unknown", String::from("")),
+        };
+        csv.push_str(&format!(
+            "{},{},{},{},{:.3}\n",
+            game,
+            winnable,
+            length,
+            report.nodes,
+            report.elapsed.as_secs_f64()
+        ));
+    }
+    if fs::write(file_name, csv).is_err() {
+        println!("Could not write {}", file_name);
+        exit(1);
+    }
+
+    let unknown = deals - won - unwinnable;
+    println!(
+        "Games {} to {}: {} won ({:.2}%), {} unwinnable, {} unknown in {:.3}s on {} threads",
+        first,
+        first + deals - 1,
+        won,
+        100.0 * won as f64 / deals as f64,
+        unwinnable,
+        unknown,
+        start.elapsed().as_secs_f64(),
+        threads
+    );
+    println!("Results written to {}", file_name);
+    info!(
+        "Batch from {}: {} won, {} unwinnable, {} unknown of {} in {:?}, written to {}",
+        first,
+        won,
+        unwinnable,
+        unknown,
+        deals,
+        start.elapsed(),
+        file_name
+    );
+}
+
+/// Draws deals until the solver wins one within `rules.winnable`, saving
+/// it to the deals known to be winnable. When time runs out one of those is
+/// picked instead.
+pub fn winnable_game(rules: &Rules) -> u64 {
+    let budget = match rules.winnable {
+        Some(b) => b,
+        None => exit(1),
+    };
+    let file_name = format!("{}/{}_{}.txt", WINNABLE_DIR, rules.name, rules.draw);
+    let known: Vec<u64> = match fs::read_to_string(&file_name) {
+        Ok(text) => text.lines().filter_map(|l| l.parse().ok()).collect(),
+        Err(_) => Vec::new(),
+    };
+    let mut rng = rand::thread_rng();
+    let start = Instant::now();
+    println!("Looking for a winnable deal...");
+    while start.elapsed() < budget {
+        let game = rng.gen_range(1, 1000000);
+        if known.contains(&game) {
+            return game;
+        }
+        let (rules, position) = Position::deal(rules, game);
+        let limits = Limits {
+            nodes: NODES,
+            time: budget.saturating_sub(start.elapsed()),
+        };
+        if let Outcome::Won(_) = solve(&rules, &position, limits).outcome {
+            save_winnable(&file_name, game);
+            info!("Game {} is winnable, found in {:?}", game, start.elapsed());
+            return game;
+        }
+    }
+    if known.is_empty() {
+        let game = rng.gen_range(1, 1000000);
+        println!("No winnable deal found, dealing game {} anyway", game);
+        info!("No winnable deal found in {:?}", budget);
+        return game;
+    }
+    let game = known[rng.gen_range(0, known.len())];
+    info!("Game {} picked from {}", game, file_name);
+    game
+}
+
+fn save_winnable(file_name: &str, game: u64) {
+    let saved = fs::create_dir_all(WINNABLE_DIR).and_then(|_| {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file_name)?;
+        writeln!(file, "{}", game)
+    });
+    if saved.is_err() {
+        info!("Could not save game {} to {}", game, file_name);
+    }
+}
+
+/// Searches depth first for a win from a position, never visiting the same
+/// position twice.
+pub fn solve(rules: &Rules, position: &Position, limits: Limits) -> Report {
+    let start = Instant::now();
+    let mut nodes: u64 = 0;
+    let zobrist = Zobrist::new(rules);
+    let mut visited = Table::new((limits.nodes as usize).saturating_mul(2));
+
+    let mut root = position.clone();
+    let mut hash = zobrist.hash(&root);
+    let arrival = autoplay(rules, &zobrist, &mut root, &mut hash);
+    if moves::game_over(rules, &root.piles) {
+        return Report {
+            outcome: Outcome::Won(arrival),
+            nodes,
+            elapsed: start.elapsed(),
+        };
+    }
+    visited.insert(hash.value(), zobrist.key(&root));
+    let untried = candidates(rules, &root);
+    let mut stack = vec![Frame {
+        position: root,
+        hash,
+        arrival,
+        untried,
+    }];
+
+    while let Some(frame) = stack.last_mut() {
+        let next = match frame.untried.pop() {
+            Some(m) => m,
+            None => {
+                stack.pop();
+                continue;
+            }
+        };
+        let mut position = frame.position.clone();
+        let mut hash = frame.hash.clone();
+        for m in next.iter() {
+            zobrist.play(&mut position, &mut hash, m);
+        }
+        let mut arrival = next;
+        arrival.append(&mut autoplay(rules, &zobrist, &mut position, &mut hash));
+        if moves::game_over(rules, &position.piles) {
+            let mut path: Vec<Move> = stack.iter().flat_map(|f| f.arrival.clone()).collect();
+            path.append(&mut arrival);
+            return Report {
+                outcome: Outcome::Won(path),
+                nodes,
+                elapsed: start.elapsed(),
+            };
+        }
+        if !visited.insert(hash.value(), zobrist.key(&position)) {
+            continue;
+        }
+        nodes += 1;
+        if nodes >= limits.nodes || start.elapsed() > limits.time {
+            return Report {
+                outcome: Outcome::Unknown,
+                nodes,
+                elapsed: start.elapsed(),
+            };
+        }
+        let untried = candidates(rules, &position);
+        stack.push(Frame {
+            position,
+            hash,
+            arrival,
+            untried,
+        });
+    }
+
+    Report {
+        outcome: Outcome::Unwinnable,
+        nodes,
+        elapsed: start.elapsed(),
+    }
+}
+
+/// Whether the game can't get any further from a position: no position
+/// reachable from it, going through the stock and moving cards around,
+/// has more cards on the foundations, fewer face down or fewer left to
+/// bring into play. Gives up, saying it isn't, after a few thousand
+/// positions.
+pub fn dead_end(rules: &Rules, position: &Position) -> bool {
+    if rules.stock == Stock::Redeal && moves::can_turn_stock(rules, position) {
+        return false;
+    }
+    let start = progress(rules, &position.piles);
+    let zobrist = Zobrist::new(rules);
+    let mut visited = Table::new(DEAD_END_NODES * 2);
+    let mut searched = 0;
+    let hash = zobrist.hash(position);
+    visited.insert(hash.value(), zobrist.key(position));
+    let mut stack = vec![(position.clone(), hash)];
+    while let Some((current, current_hash)) = stack.pop() {
+        for next in moves::legal_moves(rules, &current) {
+            let mut position = current.clone();
+            let mut hash = current_hash.clone();
+            zobrist.play(&mut position, &mut hash, &next);
+            if moves::game_over(rules, &position.piles) {
+                return false;
+            }
+            let (founded, hidden, undealt) = progress(rules, &position.piles);
+            if founded > start.0 || hidden < start.1 || undealt < start.2 {
+                return false;
+            }
+            if visited.insert(hash.value(), zobrist.key(&position)) {
+                searched += 1;
+                if searched > DEAD_END_NODES {
+                    return false;
+                }
+                stack.push((position, hash));
+            }
+        }
+    }
+    true
+}
+
+/// Cards on the foundations, face down on the tableau, and in the stock,
+/// waste and reserve.
+fn progress(rules: &Rules, piles: &[Vec<Card>]) -> (usize, usize, usize) {
+    let founded = rules.foundation_range().map(|i| piles[i].len()).sum();
+    let hidden = rules
+        .tableau_range()
+        .flat_map(|i| piles[i].iter())
+        .filter(|c| !c.is_faceup())
+        .count();
+    let reserve = match rules.reserve_index() {
+        Some(i) => piles[i].len(),
+        None => 0,
+    };
+    (founded, hidden, piles[0].len() + piles[1].len() + reserve)
+}
+
+/// Moves worth making from a position, best first: the first move of a win
+/// found by a short search, then every move getting further within two
+/// moves, and turning the stock.
+pub fn hints(rules: &Rules, position: &Position) -> Vec<Move> {
+    let mut hints: Vec<Move> = Vec::new();
+    if let Outcome::Won(path) = solve(rules, position, HINT_LIMITS).outcome {
+        if let Some(m) = path.first() {
+            hints.push(*m);
+        }
+    }
+    let now = score(rules, &position.piles);
+    let mut ranked: Vec<(i32, Move)> = moves::legal_moves(rules, position)
+        .into_iter()
+        .filter(|m| !hints.contains(m))
+        .map(|m| (lookahead(rules, position, &m), m))
+        .filter(|(s, m)| *s > now || *m == Move::Stock)
+        .collect();
+    ranked.sort_by_key(|(s, _)| std::cmp::Reverse(*s));
+    hints.extend(ranked.into_iter().map(|(_, m)| m));
+    hints
+}
+
+/// Best score reached with a move and any move after it.
+fn lookahead(rules: &Rules, position: &Position, first: &Move) -> i32 {
+    let mut next = position.clone();
+    moves::play(rules, &mut next, first);
+    let mut best = score(rules, &next.piles);
+    for m in moves::legal_moves(rules, &next) {
+        let mut after = next.clone();
+        moves::play(rules, &mut after, &m);
+        best = best.max(score(rules, &after.piles));
+    }
+    best
+}
+
+/// Cards on the foundations and empty tableau piles count for a position,
+/// cards still face down on the tableau against it.
+fn score(rules: &Rules, piles: &[Vec<Card>]) -> i32 {
+    let founded: usize = rules.foundation_range().map(|i| piles[i].len()).sum();
+    let tableau = rules.tableau_range().map(|i| &piles[i]);
+    let hidden = tableau.clone().flatten().filter(|c| !c.is_faceup()).count();
+    let empty = tableau.filter(|p| p.is_empty()).count();
+    founded as i32 * 10 - hidden as i32 * 5 + empty as i32 * 2
+}
+
+/// Legal moves worth trying, the most promising last. Moving a whole pile
+/// into an empty one only swaps the piles around, so it is left out unless
+/// which pile is which matters. Turning the stock only matters for the card
+/// it brings up, so the stock is turned as part of playing a card from the
+/// waste, and a card taken off the foundations only matters for what is
+/// built on it, so that follows at once.
+fn candidates(rules: &Rules, position: &Position) -> Vec<Vec<Move>> {
+    let turned = rules.stock == Stock::Waste || rules.stock == Stock::Turn;
+    let mut candidates: Vec<(u32, Vec<Move>)> = moves::legal_moves(rules, position)
+        .into_iter()
+        .filter(|m| match *m {
+            Move::Cards {
+                source,
+                target,
+                quantity,
+            } => {
+                rules.tableau_ordered()
+                    || !(rules.tableau_range().contains(&source)
+                        && position.piles[target].is_empty()
+                        && position.piles[source].len() == quantity)
+            }
+            Move::Stock => !turned,
+        })
+        .flat_map(|m| match m {
+            Move::Cards { source, target, .. } if rules.foundation_range().contains(&source) => {
+                built_on(rules, position, m, target)
+            }
+            _ => vec![vec![m]],
+        })
+        .map(|m| (rank(rules, position, &m), m))
+        .collect();
+    if turned {
+        for turns in from_stock(rules, position) {
+            candidates.push((rank(rules, position, &turns), turns));
+        }
+    }
+    // Moves ranked the same are tried in the order they were found, as the
+    // bot tries them.
+    candidates.sort_by_key(|(r, _)| std::cmp::Reverse(*r));
+    candidates.reverse();
+    candidates.into_iter().map(|(_, m)| m).collect()
+}
+
+/// A move followed by each move putting cards on the pile it moved to.
+fn built_on(rules: &Rules, position: &Position, first: Move, target: usize) -> Vec<Vec<Move>> {
+    let mut moved = position.clone();
+    moves::play(rules, &mut moved, &first);
+    let mut follow: Vec<Vec<Move>> = moves::legal_moves(rules, &moved)
+        .into_iter()
+        .map(|m| vec![m])
+        .collect();
+    follow.append(&mut from_stock(rules, &moved));
+    follow
+        .into_iter()
+        .filter(|f| match f.last() {
+            Some(Move::Cards { target: t, .. }) => *t == target,
+            _ => false,
+        })
+        .map(|f| {
+            let mut play = vec![first];
+            play.extend(f);
+            play
+        })
+        .collect()
+}
+
+/// Plays of every card the stock can bring to the top of the waste, each as
+/// the stock turns it takes followed by the play.
+fn from_stock(rules: &Rules, position: &Position) -> Vec<Vec<Move>> {
+    let mut plays: Vec<Vec<Move>> = Vec::new();
+    if rules.stock != Stock::Waste && rules.stock != Stock::Turn {
+        return plays;
+    }
+    let mut turned = position.clone();
+    let mut turns: Vec<Move> = Vec::new();
+    let mut recycled = false;
+    loop {
+        if !turns.is_empty() {
+            if let Some(card) = moves::get_user_card(&turned.piles, 1, 1) {
+                for target in 2..rules.piles() {
+                    if moves::accepts(rules, &turned.piles, &card, 1, target, 1) {
+                        let mut play = turns.clone();
+                        play.push(Move::Cards {
+                            source: 1,
+                            target,
+                            quantity: 1,
+                        });
+                        plays.push(play);
+                    }
+                }
+            }
+        }
+        if turned.piles[0].is_empty() {
+            if recycled {
+                return plays;
+            }
+            recycled = true;
+        }
+        if !moves::turn_stock(rules, &mut turned) {
+            return plays;
+        }
+        turns.push(Move::Stock);
+    }
+}
+
+/// How promising a candidate is, higher first: its last move scored the
+/// way the bot scores it, after the stock turns leading up to it. Taking a
+/// card off the foundations and moves the bot leaves out rank last.
+fn rank(rules: &Rules, position: &Position, play: &[Move]) -> u32 {
+    let (last, turns) = match play.split_last() {
+        Some(split) => split,
+        None => return 0,
+    };
+    if let Some(Move::Cards { source, .. }) = play.first() {
+        if rules.foundation_range().contains(source) {
+            return 0;
+        }
+    }
+    let mut before = position.clone();
+    for m in turns {
+        moves::play(rules, &mut before, m);
+    }
+    bot::score(rules, &before, last).map_or(0, |s| s + 1)
+}
+
+/// Plays every card that can go to the foundations for good, returning the
+/// moves made.
+fn autoplay(
+    rules: &Rules,
+    zobrist: &Zobrist,
+    position: &mut Position,
+    hash: &mut Hash,
+) -> Vec<Move> {
+    let mut made: Vec<Move> = Vec::new();
+    if rules.foundation != Foundation::InSuit || rules.wrap || rules.base != 1 {
+        return made;
+    }
+    loop {
+        let next = moves::legal_moves(rules, position)
+            .into_iter()
+            .find(|m| match *m {
+                Move::Cards { source, target, .. } => {
+                    rules.foundation_range().contains(&target)
+                        && !rules.foundation_range().contains(&source)
+                        && match position.piles[source].last() {
+                            Some(c) => safe(rules, &position.piles, c),
+                            None => false,
+                        }
+                }
+                Move::Stock => false,
+            });
+        match next {
+            Some(m) => {
+                zobrist.play(position, hash, &m);
+                made.push(m);
+            }
+            None => return made,
+        }
+    }
+}
+
+/// A card is safe on the foundations once every card that could be built on
+/// it on the tableau is already there.
+fn safe(rules: &Rules, piles: &[Vec<Card>], card: &Card) -> bool {
+    let value = card.get_value();
+    if value <= 2 {
+        return true;
+    }
+    let suits = match rules.build {
+        Build::AlternateColors => 2,
+        Build::SameSuit => 1,
+        Build::AnySuit => 4,
+        Build::FromWaste | Build::None => return true,
+    };
+    let lower = rules
+        .foundation_range()
+        .filter(|i| match piles[*i].last() {
+            Some(c) => {
+                c.get_value() + 1 >= value
+                    && match rules.build {
+                        Build::AlternateColors => c.get_card_color() != card.get_card_color(),
+                        Build::SameSuit => c.get_card_suit() == card.get_card_suit(),
+                        _ => true,
+                    }
+            }
+            None => false,
+        })
+        .count();
+    lower >= suits * rules.decks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::{CardType, CardValue};
+
+    const LIMITS: Limits = Limits {
+        nodes: 100_000,
+        time: Duration::from_secs(60),
+    };
+
+    #[test]
+    fn solution_wins_the_deal() {
+        let (rules, start) = Position::deal(&Rules::klondike(), 77);
+        let path = match solve(&rules, &start, LIMITS).outcome {
+            Outcome::Won(path) => path,
+            outcome => panic!("klondike 77 is winnable, got {:?}", outcome),
+        };
+        let mut position = start;
+        for next in path.iter() {
+            assert!(moves::legal_moves(&rules, &position).contains(next));
+            moves::play(&rules, &mut position, next);
+        }
+        assert!(moves::game_over(&rules, &position.piles));
+    }
+
+    #[test]
+    fn deal_with_no_win_is_unwinnable() {
+        let (rules, start) = Position::deal(&Rules::canfield(), 5);
+        assert!(matches!(
+            solve(&rules, &start, LIMITS).outcome,
+            Outcome::Unwinnable
+        ));
+    }
+
+    #[test]
+    fn search_gives_up_at_the_node_limit() {
+        let (rules, start) = Position::deal(&Rules::klondike(), 12);
+        let limits = Limits {
+            nodes: 10,
+            ..LIMITS
+        };
+        let report = solve(&rules, &start, limits);
+        assert!(matches!(report.outcome, Outcome::Unknown));
+        assert_eq!(report.nodes, 10);
+    }
+
+    #[test]
+    fn huge_node_limit_does_not_overflow() {
+        let (rules, start) = Position::deal(&Rules::klondike(), 77);
+        let limits = Limits {
+            nodes: u64::MAX,
+            ..LIMITS
+        };
+        assert!(matches!(
+            solve(&rules, &start, limits).outcome,
+            Outcome::Won(_)
+        ));
+    }
+
+    #[test]
+    fn whole_pile_moves_are_kept_when_piles_are_told_apart() {
+        let (rules, mut position) = Position::deal(&Rules::scorpion(), 1);
+        let mut king = Card::new(CardType::Corazones, CardValue::K);
+        king.show();
+        position.piles[2].clear();
+        position.piles[3] = vec![king];
+        let whole = Move::Cards {
+            source: 3,
+            target: 2,
+            quantity: 1,
+        };
+        assert!(moves::legal_moves(&rules, &position).contains(&whole));
+        assert!(candidates(&rules, &position).contains(&vec![whole]));
+    }
+
+    fn shown(suit: CardType, value: CardValue) -> Card {
+        let mut card = Card::new(suit, value);
+        card.show();
+        card
+    }
+
+    /// A Klondike game with a nine of hearts on a face down card and the
+    /// stock gone through once, turning up nothing the game takes.
+    fn stuck(rules: &Rules) -> Position {
+        let mut position = Position {
+            piles: vec![Vec::new(); rules.piles()],
+            game: 1,
+            passes: 1,
+        };
+        position.piles[1] = vec![
+            shown(CardType::Espadas, CardValue::N(3)),
+            shown(CardType::Diamantes, CardValue::N(7)),
+        ];
+        position.piles[2] = vec![
+            Card::new(CardType::Espadas, CardValue::N(5)),
+            shown(CardType::Corazones, CardValue::N(9)),
+        ];
+        position
+    }
+
+    #[test]
+    fn stuck_position_is_a_dead_end() {
+        let rules = Rules::klondike();
+        assert!(dead_end(&rules, &stuck(&rules)));
+    }
+
+    #[test]
+    fn playable_waste_card_is_no_dead_end() {
+        let rules = Rules::klondike();
+        let mut position = stuck(&rules);
+        position.piles[1].push(shown(CardType::Espadas, CardValue::N(8)));
+        assert!(!dead_end(&rules, &position));
+    }
+
+    #[test]
+    fn card_that_can_be_turned_over_is_no_dead_end() {
+        let rules = Rules::klondike();
+        let mut position = stuck(&rules);
+        position.piles[3] = vec![shown(CardType::Espadas, CardValue::Z)];
+        assert!(!dead_end(&rules, &position));
+    }
+
+    #[test]
+    fn hints_start_with_the_winning_move() {
+        let (rules, start) = Position::deal(&Rules::klondike(), 77);
+        let first = match solve(&rules, &start, HINT_LIMITS).outcome {
+            Outcome::Won(path) => path[0],
+            outcome => panic!("klondike 77 is winnable, got {:?}", outcome),
+        };
+        let found = hints(&rules, &start);
+        assert_eq!(found[0], first);
+        let now = score(&rules, &start.piles);
+        for m in found[1..].iter() {
+            assert_ne!(*m, first);
+            assert!(*m == Move::Stock || lookahead(&rules, &start, m) > now);
+        }
+        assert!(found.contains(&Move::Stock));
+    }
+}
@@ -0,0 +1,334 @@
+use crate::card::Card;
+use crate::deck;
+use crate::input::{read_key, Add};
+use crossterm::event::KeyCode;
+use log::info;
+use rand::Rng;
+use std::process::exit;
+use undo::Record;
+
+const SLOTS: usize = 28;
+const ROWS: [std::ops::Range<usize>; 4] = [0..3, 3..9, 9..18, 18..28];
+const KEYS: &str = "1234567890asdfgjkl";
+const PEAK_BONUS: i32 = 15;
+const TRIPEAKS_BONUS: i32 = 30;
+
+/// Which slots of the three peaks lie on top of each other. A slot is
+/// covered by the slots listed in `covered_by` and its card becomes face up
+/// and playable once all of them have been removed.
+struct Layout {
+    covered_by: Vec<Vec<usize>>,
+    columns: Vec<usize>,
+}
+
+impl Layout {
+    fn new() -> Self {
+        let mut covered_by: Vec<Vec<usize>> = Vec::with_capacity(SLOTS);
+        for peak in 0..3 {
+            covered_by.push(vec![3 + 2 * peak, 4 + 2 * peak]);
+        }
+        for k in 0..6 {
+            let peak = k / 2;
+            covered_by.push(vec![9 + k + peak, 10 + k + peak]);
+        }
+        for k in 0..9 {
+            covered_by.push(vec![18 + k, 19 + k]);
+        }
+        covered_by.resize(SLOTS, Vec::new());
+
+        // The bottom row is spread evenly and every other card sits centered
+        // over the two cards covering it.
+        let mut columns: Vec<usize> = vec![0; SLOTS];
+        for slot in (0..SLOTS).rev() {
+            columns[slot] = match covered_by[slot].as_slice() {
+                [left, right] => (columns[*left] + columns[*right]) / 2,
+                _ => 4 * (slot - 18),
+            };
+        }
+
+        Self {
+            covered_by,
+            columns,
+        }
+    }
+
+    fn is_exposed(&self, peaks: &[Option<Card>], slot: usize) -> bool {
+        self.covered_by[slot].iter().all(|s| peaks[*s].is_none())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Table {
+    peaks: Vec<Option<Card>>,
+    stock: Vec<Card>,
+    waste: Vec<Card>,
+    score: i32,
+    streak: i32,
+}
+
+pub fn start_game(mut game: u64, wrap: bool) {
+    if game == 0 {
+        let mut rng = rand::thread_rng();
+        game = rng.gen_range(1, 1000000);
+    }
+    let layout = Layout::new();
+    let mut table = set_up(&layout, game);
+    let mut record = Record::default();
+    match record.apply(Add(table.clone())) {
+        Ok(_) => {}
+        Err(_) => exit(1),
+    }
+
+    print!("\x1B[2J\x1B[1;1H");
+    print_table(&layout, &table);
+    info!("New Game!\n{}", table_to_log(&table));
+
+    loop {
+        if game_over(&layout, &table, wrap) {
+            println!("Game Over! Score: {}", table.score);
+            info!(
+                "Game Over! Score: {}\n{}",
+                table.score,
+                table_to_log(&table)
+            );
+            return;
+        }
+        let key = read_key();
+        print!("\x1B[2J\x1B[1;1H");
+        match key {
+            KeyCode::Enter => {
+                if draw(&mut table) {
+                    match record.apply(Add(table.clone())) {
+                        Ok(_) => {}
+                        Err(_) => exit(1),
+                    }
+                    info!("From Hand to Waste\n{}", table_to_log(&table));
+                } else {
+                    println!("No cards left in the stock");
+                }
+            }
+            KeyCode::Char('u') | KeyCode::Char('U') => {
+                if record.current() > 1 {
+                    match record.undo() {
+                        Ok(_) => {}
+                        Err(_) => exit(1),
+                    }
+                    restore(&mut table, &record);
+                    info!("Undo\n{}", table_to_log(&table));
+                }
+            }
+            KeyCode::Char('i') | KeyCode::Char('I') => {
+                if record.current() < record.len() {
+                    match record.redo() {
+                        Ok(_) => {}
+                        Err(_) => exit(1),
+                    }
+                    restore(&mut table, &record);
+                    info!("Redo\n{}", table_to_log(&table));
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') => {
+                start_game(0, wrap);
+                return;
+            }
+            KeyCode::Esc => return,
+            KeyCode::Char(c) => match slot_for_key(&layout, &table, c) {
+                Some(slot) => {
+                    if play(&layout, &mut table, slot, wrap) {
+                        match record.apply(Add(table.clone())) {
+                            Ok(_) => {}
+                            Err(_) => exit(1),
+                        }
+                        info!("From {} to Waste\n{}", c, table_to_log(&table));
+                    } else {
+                        println!("No moves");
+                    }
+                }
+                None => println!("Invalid command"),
+            },
+            _ => println!("Invalid command"),
+        }
+        print_table(&layout, &table);
+    }
+}
+
+fn set_up(layout: &Layout, game: u64) -> Table {
+    let mut initial_deck = deck::new_deck(1);
+    deck::shuffle_deck(&mut initial_deck, game);
+
+    let mut peaks: Vec<Option<Card>> = Vec::with_capacity(SLOTS);
+    for _ in 0..SLOTS {
+        match initial_deck.pop() {
+            Some(c) => peaks.push(Some(c)),
+            None => exit(1),
+        }
+    }
+
+    let mut table = Table {
+        peaks,
+        stock: initial_deck,
+        waste: Vec::with_capacity(52),
+        score: 0,
+        streak: 0,
+    };
+    uncover(layout, &mut table);
+    draw(&mut table);
+    table
+}
+
+fn restore(table: &mut Table, record: &Record<Vec<Table>>) {
+    match record.target().get(record.current() - 1) {
+        Some(t) => *table = t.clone(),
+        None => exit(1),
+    }
+}
+
+fn uncover(layout: &Layout, table: &mut Table) {
+    for slot in 0..SLOTS {
+        if layout.is_exposed(&table.peaks, slot) {
+            if let Some(c) = table.peaks[slot].as_mut() {
+                c.show();
+            }
+        }
+    }
+}
+
+fn draw(table: &mut Table) -> bool {
+    match table.stock.pop() {
+        Some(mut c) => {
+            c.show();
+            table.waste.push(c);
+            table.streak = 0;
+            true
+        }
+        None => false,
+    }
+}
+
+/// A card can be played on the waste when it is one rank above or below its
+/// top card, with kings and aces next to each other when `wrap` is set.
+fn valid_move(card: &Card, waste: &[Card], wrap: bool) -> bool {
+    match waste.last() {
+        Some(top) => {
+            let (a, b) = (top.get_value(), card.get_value());
+            if a + 1 == b || b + 1 == a {
+                return true;
+            }
+            wrap && ((a == 13 && b == 1) || (a == 1 && b == 13))
+        }
+        None => true,
+    }
+}
+
+fn play(layout: &Layout, table: &mut Table, slot: usize, wrap: bool) -> bool {
+    let card = match table.peaks[slot] {
+        Some(c) => c,
+        None => return false,
+    };
+    if !valid_move(&card, &table.waste, wrap) {
+        return false;
+    }
+
+    table.peaks[slot] = None;
+    table.waste.push(card);
+    table.streak += 1;
+    table.score += table.streak;
+    if ROWS[0].contains(&slot) {
+        table.score += PEAK_BONUS;
+        if ROWS[0].clone().all(|s| table.peaks[s].is_none()) {
+            table.score += TRIPEAKS_BONUS;
+        }
+    }
+    uncover(layout, table);
+    true
+}
+
+fn exposed_slots(layout: &Layout, table: &Table) -> Vec<usize> {
+    let mut slots: Vec<usize> = (0..SLOTS)
+        .filter(|s| table.peaks[*s].is_some() && layout.is_exposed(&table.peaks, *s))
+        .collect();
+    slots.sort_by_key(|s| layout.columns[*s]);
+    slots
+}
+
+fn slot_for_key(layout: &Layout, table: &Table, key: char) -> Option<usize> {
+    let position = KEYS.find(key)?;
+    exposed_slots(layout, table).get(position).copied()
+}
+
+fn game_over(layout: &Layout, table: &Table, wrap: bool) -> bool {
+    if table.peaks.iter().all(|c| c.is_none()) {
+        return true;
+    }
+    if !table.stock.is_empty() {
+        return false;
+    }
+    exposed_slots(layout, table)
+        .iter()
+        .all(|s| match table.peaks[*s] {
+            Some(c) => !valid_move(&c, &table.waste, wrap),
+            None => true,
+        })
+}
+
+fn print_table(layout: &Layout, table: &Table) {
+    let stock = if table.stock.is_empty() { "___" } else { "XXX" };
+    let waste = match table.waste.last() {
+        Some(c) => format!("{}", c),
+        None => String::from("___"),
+    };
+    println!(
+        "{} {}    Score: {}  Streak: {}",
+        stock, waste, table.score, table.streak
+    );
+
+    let exposed = exposed_slots(layout, table);
+    for row in ROWS.iter() {
+        let mut cards = String::from("");
+        let mut keys = String::from("");
+        let mut column = 0;
+        for slot in row.clone() {
+            let x = layout.columns[slot];
+            cards.push_str(&" ".repeat(x - column));
+            keys.push_str(&" ".repeat(x - column));
+            match table.peaks[slot] {
+                Some(c) => cards.push_str(&format!("{}", c)),
+                None => cards.push_str("   "),
+            }
+            match exposed.iter().position(|s| *s == slot) {
+                Some(p) => match KEYS.chars().nth(p) {
+                    Some(k) => keys.push_str(&format!(" {} ", k)),
+                    None => keys.push_str("   "),
+                },
+                None => keys.push_str("   "),
+            }
+            column = x + 3;
+        }
+        println!();
+        println!("{}", cards.trim_end());
+        print!("{}", keys.trim_end());
+    }
+    println!();
+}
+
+fn table_to_log(table: &Table) -> String {
+    let mut log_string = String::from("");
+    match table.stock.last() {
+        Some(c) => c.log(&mut log_string),
+        None => log_string.push_str("___ "),
+    }
+    match table.waste.last() {
+        Some(c) => c.log(&mut log_string),
+        None => log_string.push_str("___ "),
+    }
+    for row in ROWS.iter() {
+        log_string.push('\n');
+        for slot in row.clone() {
+            match table.peaks[slot] {
+                Some(c) => c.log(&mut log_string),
+                None => log_string.push_str("    "),
+            }
+        }
+    }
+    log_string
+}
@@ -0,0 +1,316 @@
+use crate::card::Card;
+use crate::moves::{self, Move, Position};
+use crate::rules::{Rules, Stock};
+use std::process::exit;
+
+const SEED: u64 = 0x5EED;
+/// Deepest a pile gets: every card of two decks.
+const DEPTH: usize = 104;
+/// Card states: every card of a deck, face down or face up.
+const STATES: usize = 104;
+const PILES: usize = 32;
+/// Most positions a table keeps.
+const MAX_SLOTS: usize = 1 << 21;
+const BUCKET: usize = 4;
+
+/// A key for every card state at every depth, then a salt for every pile,
+/// worked out when the program is built.
+static KEYS: [u64; DEPTH * STATES + PILES] = keys();
+
+const fn keys() -> [u64; DEPTH * STATES + PILES] {
+    let mut keys = [0; DEPTH * STATES + PILES];
+    let mut state = SEED;
+    let mut i = 0;
+    while i < keys.len() {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        keys[i] = splitmix(state);
+        i += 1;
+    }
+    keys
+}
+
+/// splitmix64 finaliser
+const fn splitmix(mut h: u64) -> u64 {
+    h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    h ^ (h >> 31)
+}
+
+/// Hashes positions with a random key for every card at every depth of a
+/// pile. A pile hashes to the keys of its cards XORed together, and a
+/// position to the sum of its mixed pile hashes. The tableau piles aren't
+/// told apart when their order doesn't matter, so positions that only swap
+/// them around hash the same.
+#[derive(Debug, Clone)]
+pub struct Zobrist<'a> {
+    ordered: bool,
+    rules: &'a Rules,
+}
+
+/// Hash of a position, kept up to date move by move.
+#[derive(Debug, Clone)]
+pub struct Hash {
+    piles: Vec<u64>,
+    board: u64,
+    passes: usize,
+}
+
+impl Hash {
+    pub fn value(&self) -> u64 {
+        self.board ^ (self.passes as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+}
+
+impl<'a> Zobrist<'a> {
+    pub fn new(rules: &'a Rules) -> Self {
+        if rules.decks * 52 > DEPTH || rules.piles() > PILES {
+            println!("Too many cards or piles to hash");
+            exit(1);
+        }
+        Self {
+            ordered: rules.tableau_ordered(),
+            rules,
+        }
+    }
+
+    pub fn hash(&self, position: &Position) -> Hash {
+        let piles: Vec<u64> = (0..position.piles.len())
+            .map(|i| self.cards(&position.piles[i], 0))
+            .collect();
+        let board = piles
+            .iter()
+            .enumerate()
+            .fold(0u64, |sum, (i, h)| sum.wrapping_add(self.mix(i, *h)));
+        Hash {
+            piles,
+            board,
+            passes: self.passes(position),
+        }
+    }
+
+    /// Makes a move like `moves::play`, updating the hash of the piles it
+    /// changes from the deepest card it changes up.
+    pub fn play(&self, position: &mut Position, hash: &mut Hash, next: &Move) {
+        let changes = self.changes(position, next);
+        for (i, depth) in changes.iter() {
+            hash.board = hash.board.wrapping_sub(self.mix(*i, hash.piles[*i]));
+            hash.piles[*i] ^= self.cards(&position.piles[*i], *depth);
+        }
+        moves::play(self.rules, position, next);
+        for (i, depth) in changes.iter() {
+            hash.piles[*i] ^= self.cards(&position.piles[*i], *depth);
+            hash.board = hash.board.wrapping_add(self.mix(*i, hash.piles[*i]));
+        }
+        hash.passes = self.passes(position);
+    }
+
+    /// Piles a move changes, each with the depth the change starts at.
+    fn changes(&self, position: &Position, next: &Move) -> Vec<(usize, usize)> {
+        let rules = self.rules;
+        let piles = &position.piles;
+        let mut changes: Vec<(usize, usize)> = Vec::new();
+        match *next {
+            Move::Stock => match rules.stock {
+                Stock::Waste | Stock::Turn => {
+                    if piles[0].is_empty() {
+                        changes.push((0, 0));
+                        changes.push((1, 0));
+                    } else {
+                        changes.push((0, piles[0].len().saturating_sub(rules.draw)));
+                        changes.push((1, piles[1].len()));
+                    }
+                }
+                Stock::Tableau => {
+                    let dealt = rules.tableaus.len();
+                    changes.push((0, piles[0].len().saturating_sub(dealt)));
+                    changes.extend(rules.tableau_range().map(|i| (i, piles[i].len())));
+                }
+                Stock::Redeal => changes.extend(rules.tableau_range().map(|i| (i, 0))),
+                Stock::None => {}
+            },
+            Move::Cards {
+                source,
+                target,
+                quantity,
+            } => {
+                changes.push((source, piles[source].len().saturating_sub(quantity + 1)));
+                changes.push((target, piles[target].len()));
+                if let Some(i) = rules.reserve_index() {
+                    if i != source {
+                        changes.push((i, piles[i].len().saturating_sub(2)));
+                    }
+                }
+            }
+        }
+        changes
+    }
+
+    /// Keys of the cards of a pile from a depth up, XORed together.
+    fn cards(&self, pile: &[Card], depth: usize) -> u64 {
+        pile.iter().enumerate().skip(depth).fold(0, |h, (d, c)| {
+            let state = c.id() as usize * 2 + c.is_faceup() as usize;
+            h ^ KEYS[d * STATES + state]
+        })
+    }
+
+    fn mix(&self, index: usize, pile: u64) -> u64 {
+        if !self.ordered && self.rules.tableau_range().contains(&index) {
+            splitmix(pile)
+        } else {
+            splitmix(pile ^ KEYS[DEPTH * STATES + index])
+        }
+    }
+
+    /// Identifies a position exactly, telling apart what the hash leaves
+    /// the same: tableau piles are sorted when their order doesn't matter.
+    pub fn key(&self, position: &Position) -> Box<[u8]> {
+        let mut piles: Vec<Vec<u8>> = position
+            .piles
+            .iter()
+            .map(|p| {
+                p.iter()
+                    .map(|c| if c.is_faceup() { c.id() | 64 } else { c.id() })
+                    .collect()
+            })
+            .collect();
+        if !self.ordered {
+            piles[self.rules.tableau_range()].sort();
+        }
+        let mut key: Vec<u8> = Vec::with_capacity(52 * self.rules.decks + piles.len() + 1);
+        for pile in piles {
+            key.extend(pile);
+            key.push(255);
+        }
+        key.push(self.passes(position) as u8);
+        key.into_boxed_slice()
+    }
+
+    /// Passes only tell positions apart when they are redeals.
+    fn passes(&self, position: &Position) -> usize {
+        match self.rules.stock {
+            Stock::Redeal => position.passes,
+            _ => 0,
+        }
+    }
+}
+
+/// Positions already searched, as many as fit, in buckets of a few found
+/// by their hash. A position is only taken as seen when its key matches, so
+/// a hash two positions share can't hide one of them. A position pushed out
+/// of a full bucket by another one is searched again when it comes back.
+#[derive(Debug, Clone)]
+pub struct Table {
+    hashes: Vec<u64>,
+    keys: Vec<Option<Box<[u8]>>>,
+    mask: usize,
+}
+
+impl Table {
+    /// A table with room for at least `capacity` positions, up to a limit.
+    pub fn new(capacity: usize) -> Self {
+        let size = capacity.clamp(BUCKET, MAX_SLOTS).next_power_of_two();
+        Self {
+            hashes: vec![0; size],
+            keys: vec![None; size],
+            mask: size - BUCKET,
+        }
+    }
+
+    /// Records a position by its hash and key. Returns false when it was
+    /// already there.
+    pub fn insert(&mut self, value: u64, key: Box<[u8]>) -> bool {
+        let start = value as usize & self.mask;
+        for i in start..start + BUCKET {
+            match &self.keys[i] {
+                Some(k) => {
+                    if self.hashes[i] == value && *k == key {
+                        return false;
+                    }
+                }
+                None => {
+                    self.hashes[i] = value;
+                    self.keys[i] = Some(key);
+                    return true;
+                }
+            }
+        }
+        let i = start + (value >> 60) as usize % BUCKET;
+        self.hashes[i] = value;
+        self.keys[i] = Some(key);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::GAMES;
+
+    #[test]
+    fn play_keeps_the_hash_of_the_position() {
+        for name in GAMES.iter() {
+            for game in 1..=5 {
+                let (rules, mut position) = Position::deal(&Rules::from_name(name).unwrap(), game);
+                let zobrist = Zobrist::new(&rules);
+                let mut hash = zobrist.hash(&position);
+                let walk = moves::random_walk(&rules, &position, 300);
+                for (step, next) in walk.iter().enumerate() {
+                    zobrist.play(&mut position, &mut hash, next);
+                    assert_eq!(
+                        hash.value(),
+                        zobrist.hash(&position).value(),
+                        "{} game {} after {} moves",
+                        name,
+                        game,
+                        step + 1
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn swapped_tableau_piles_hash_the_same_when_unordered() {
+        let (rules, position) = Position::deal(&Rules::klondike(), 1);
+        let mut swapped = position.clone();
+        swapped.piles.swap(2, 3);
+        let zobrist = Zobrist::new(&rules);
+        assert_eq!(
+            zobrist.hash(&position).value(),
+            zobrist.hash(&swapped).value()
+        );
+        assert_eq!(zobrist.key(&position), zobrist.key(&swapped));
+
+        let (rules, position) = Position::deal(&Rules::scorpion(), 1);
+        let mut swapped = position.clone();
+        swapped.piles.swap(2, 3);
+        let zobrist = Zobrist::new(&rules);
+        assert_ne!(zobrist.key(&position), zobrist.key(&swapped));
+    }
+
+    #[test]
+    fn table_finds_what_was_inserted() {
+        let mut table = Table::new(16);
+        assert!(table.insert(7, Box::new([1, 2, 3])));
+        assert!(!table.insert(7, Box::new([1, 2, 3])));
+        assert!(table.insert(8, Box::new([1, 2, 3])));
+    }
+
+    #[test]
+    fn table_tells_apart_positions_sharing_a_hash() {
+        let mut table = Table::new(16);
+        assert!(table.insert(7, Box::new([1, 2, 3])));
+        assert!(table.insert(7, Box::new([3, 2, 1])));
+        assert!(!table.insert(7, Box::new([3, 2, 1])));
+        assert!(!table.insert(7, Box::new([1, 2, 3])));
+    }
+
+    #[test]
+    fn full_table_forgets_instead_of_growing() {
+        let mut table = Table::new(0);
+        for i in 0..100u8 {
+            assert!(table.insert(0, Box::new([i])));
+        }
+        assert_eq!(table.keys.len(), BUCKET);
+    }
+}